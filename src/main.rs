@@ -11,14 +11,19 @@ static GLOBAL: Jemalloc = Jemalloc;
 mod utils;
 use utils::{
     attr::attr,
+    dedup::dedup,
     diff::diff,
+    filter::filter,
     findgap::findgap,
     findseq::findseq,
     getseq::getseq,
     reform::reform,
+    rename::RenameOpt,
     sample::sample,
+    sort::sort,
     split::{splits, splitr},
-    stat::{stat, sum_fx},
+    stat::{stat, sum_fx, Format, StatOpt},
+    validate::validate,
 };
 
 pub const VERSION: &str = include_str!(concat!(env!("OUT_DIR"), "/VERSION"));
@@ -34,7 +39,7 @@ fn main() {
         .global_setting(AppSettings::DeriveDisplayOrder)
         .arg(
             Arg::new("input")
-                .help("input file ...")
+                .help("input file ..., FASTA/Q (plain, gz, zst, bz2 or xz) or a fofn of them")
                 .multiple_occurrences(true)
                 .global(true)
         )
@@ -45,21 +50,73 @@ fn main() {
                 .value_name("STR")
                 .help(indoc!{"
                     get sequence attributes, id:len:x:qs
+                      id:  sequence ID
+                      des: sequence description
+                      index:  1-based index of the record in its file, counted
+                              per file of a fofn
                       len: sequences length
                       x:   count x, case sensitive, where x can be a single base
                            or multiple bases (gcGC means count g + c + G + C)
+                      count.x:
+                           same as x, the form of char counts in filter
+                      gc_frac, n_frac:
+                           fraction of G + C or N bases, case insensitive
+                      hp_max:  longest homopolymer length
+                      entropy: Shannon entropy (bits) of the base composition
+                      dust:    mean DUST low-complexity score of 64 bp windows
                       qs:  quality score, for FASTQ,
-                      qsont:  quality score, for FASTQ from dorado"
+                      qsont:  quality score, for FASTQ from dorado
+                      q_min, q_max, q_median:
+                           minimum, maximum and median base quality, for FASTQ
+                      q20, q30:
+                           count of bases with quality >= 20 or 30, for FASTQ
+                      tag.KEY:
+                           value of KEY=value or SAM-style KEY:T:value in the
+                           description, case sensitive, NA if missing
+                      motif.SEQ, motif2.SEQ:
+                           non-overlapping count of SEQ, case insensitive, on the
+                           forward strand, or on each strand for motif2, where a
+                           reverse-complement palindrome is counted once
+                      re.REGEX, re2.REGEX:
+                           non-overlapping count of REGEX matches, on the forward
+                           strand, or on each strand for re2"
                     })
                 .takes_value(true),
         )
+        .arg(
+            Arg::new("header")
+                .long("header")
+                .requires("attr")
+                .help("output a header line of the field names, co-used with -a")
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("STR")
+                .default_value("tsv")
+                .possible_values(["tsv", "json"])
+                .requires("attr")
+                .help("output format of -a, json outputs one object per line")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("threads")
+                .short('t')
+                .long("threads")
+                .value_name("INT")
+                .default_value("1")
+                .help("number of threads for -a, the output keeps the input order")
+                .takes_value(true),
+        )
         .arg(
             Arg::new("reform")
                 .short('r')
                 .long("reform")
                 .value_name("STR")
                 .help(indoc!{"
-                    reform/modify the sequence, accept values:
+                    reform/modify the sequence, accept values or a comma-separated chain of
+                    them applied in order in one pass, e.g. upper,rc,line80 or fq2fa,lower,
+                    where lineINT and linkINT may only be the last step:
                       lower:    convert sequences into lowercase
                       upper:    convert sequences into uppercase
                       fq2fa:    converts FASTQ to FASTA
@@ -70,12 +127,125 @@ fn main() {
                       lineINT[c]:  
                                 wrap sequences into INT characters per line, 
                                 0 for no wrap, c is the alignment mode, 
-                                color for SNVs and INDELs
-                      linkINT:  link sequences with INT Ns"
+                                color for SNVs and INDELs, not chained
+                      linkINT:  link sequences with INT Ns
+                    trim the sequence and quality, drop reads shorter than --min_len:
+                      qtrimINT[,INT]:
+                                sliding-window quality trimming from both ends, cut
+                                until the mean quality of a window (default 4 bp)
+                                reaches INT, for FASTQ
+                      cropINT[,INT]:
+                                remove INT bases from the head and INT (default 0)
+                                from the tail
+                      onthead[INT]:
+                                remove the first INT (default 60) low-quality bases
+                                of ONT reads at least as long, as qsont of -a
+                      polya[INT]:
+                                trim poly-A tails and poly-T heads of at least INT
+                                (default 10) bases
+                      trimn:    trim leading and trailing Ns
+                    translate, may only be followed by lineINT:
+                      trans[FRAME]:
+                                translate FRAME 1, 2, 3, -1, -2, -3 (default 1) or 6
+                                for all six frames, IDs are tagged as ID_f+1, see
+                                --table and --stop"
                       // splitINT: split sequences at Ns with length >= INT
                 })
                 .takes_value(true)
         )
+        .arg(
+            Arg::new("min_len")
+                .long("min_len")
+                .value_name("INT")
+                .default_value("1")
+                .requires("reform")
+                .help("minimum read length after trimming with -r")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("table")
+                .long("table")
+                .value_name("INT")
+                .default_value("1")
+                .requires("reform")
+                .help("NCBI genetic code table of -r trans")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("stop")
+                .long("stop")
+                .value_name("STR")
+                .default_value("mark")
+                .possible_values(["mark", "trim"])
+                .requires("reform")
+                .help("mark stop codons as * or end proteins at the first one, for -r trans")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("id_re")
+                .long("id_re")
+                .value_name("REGEX")
+                .requires("reform")
+                .help("replace matches of REGEX in IDs with --id_rep, co-used with -r")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("id_rep")
+                .long("id_rep")
+                .value_name("STR")
+                .default_value("")
+                .requires("id_re")
+                .help("replacement of --id_re, $1 or ${name} for groups")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("prefix")
+                .long("prefix")
+                .value_name("STR")
+                .requires("reform")
+                .help("add a prefix to IDs, {file} for the input file name without extensions, \
+                    co-used with -r")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("suffix")
+                .long("suffix")
+                .value_name("STR")
+                .requires("reform")
+                .help("add a suffix to IDs, {file} as --prefix, co-used with -r")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("renumber")
+                .long("renumber")
+                .value_name("STR")
+                .requires("reform")
+                .help("rename records as STR_1, STR_2, ..., co-used with -r")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("no_des")
+                .long("no_des")
+                .requires("reform")
+                .help("drop the descriptions, co-used with -r")
+        )
+        .arg(
+            Arg::new("id_map")
+                .long("id_map")
+                .value_name("FILE")
+                .requires("reform")
+                .help("rename IDs from a two-column (old new) mapping file, co-used with -r")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("map_out")
+                .long("map_out")
+                .value_name("FILE")
+                .requires("reform")
+                .help("write the output and input IDs to FILE, which undoes the header operations \
+                    by --id_map, an identity map if there is no header operation")
+                .takes_value(true),
+        )
         .arg(
             Arg::new("split")
                 .short('p')
@@ -131,14 +301,107 @@ fn main() {
                         .value_name("int[G|M|K]")
                         .default_value("0")
                         // .requires("n_len")
-                        .help("genome size, 0 means calculate genome size using the input file, co-used with -n")
+                        .help("genome size, output NGx, LGx and auNG against it if > 0")
                         .takes_value(true),
                 )
                 .arg(
                     Arg::new("out_ctg")
                         .short('o')
                         .long("out_ctg")
-                        .help("output contig sequences to <INPUT>.ctg.fa")
+                        .help("output contig sequences to <INPUT>.ctg.fa, and their placement in scaffolds to <INPUT>.ctg.agp")
+                )
+                .arg(
+                    Arg::new("per_file")
+                        .short('p')
+                        .long("per_file")
+                        .help("output one row per input file, -s is ignored")
+                )
+                .arg(
+                    Arg::new("all")
+                        .short('a')
+                        .long("all")
+                        .requires("per_file")
+                        .help("add a row for all input files, co-used with -p")
+                )
+                .arg(
+                    Arg::new("format")
+                        .short('f')
+                        .long("format")
+                        .value_name("STR")
+                        .default_value("table")
+                        .possible_values(["table", "tsv", "json"])
+                        .help("output format, tsv and json use stable field names for downstream parsing")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("nx")
+                        .short('x')
+                        .long("nx")
+                        .value_name("INT,...")
+                        .help("Nx thresholds in 1-100, [default: 10,20,...,90, or 50,90 with -p]")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("nx_curve")
+                        .short('c')
+                        .long("nx_curve")
+                        .conflicts_with("step_len")
+                        .help("output the full Nx curve (x = 1-100) instead of the report")
+                )
+                .arg(
+                    Arg::new("threads")
+                        .short('t')
+                        .long("threads")
+                        .value_name("INT")
+                        .default_value("1")
+                        .help("number of threads, files are read in parallel, and BGZF blocks are decompressed \
+                            in parallel with the threads left, -o is not affected")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("stream")
+                        .short('S')
+                        .long("stream")
+                        .help("count lengths in constant memory, exact below 1 Mbp and binned by ~1.6% above")
+                )
+                .arg(
+                    Arg::new("fai")
+                        .short('i')
+                        .long("fai")
+                        .conflicts_with("n_len")
+                        .help("read lengths from <INPUT>.fai instead of scanning sequences, no base or quality stat")
+                )
+                .arg(
+                    Arg::new("hist_bins")
+                        .long("hist_bins")
+                        .value_name("INT")
+                        .default_value("30")
+                        .help("number of histogram bins, including the bins of lengths out of the range")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("hist_log")
+                        .long("hist_log")
+                        .help("use log10-scaled histogram bins over all lengths")
+                )
+                .arg(
+                    Arg::new("hist_min")
+                        .long("hist_min")
+                        .value_name("int[G|M|K]")
+                        .help("lower bound of the histogram range, shorter lengths go to the first bin")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("hist_max")
+                        .long("hist_max")
+                        .value_name("int[G|M|K]")
+                        .help("upper bound of the histogram range, longer lengths go to the last bin")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("hist_bases")
+                        .long("hist_bases")
+                        .help("weight the histogram by bases instead of reads")
                 )
         )
         .subcommand(
@@ -210,15 +473,144 @@ fn main() {
                         .help("compare sequences with same ID base by base")
                 )
         )
+        .subcommand(
+            App::new("validate")
+                .about("check FASTA/Q files strictly, print each problem as: file, record, line, \
+                    byte offset (0-based, decompressed), kind and message, exit with 1 if any")
+        )
+        .subcommand(
+            App::new("dedup")
+                .about("remove duplicate records, keep the first one")
+                .arg(
+                    Arg::new("mode")
+                        .short('m')
+                        .long("mode")
+                        .value_name("STR")
+                        .default_value("seq")
+                        .possible_values(["id", "seq", "rc"])
+                        .help("duplicates by ID, by sequence, or by sequence with reverse complements \
+                            treated as equal")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("ignore-case")
+                        .short('i')
+                        .long("ignore-case")
+                        .help("ignore case of sequences")
+                )
+                .arg(
+                    Arg::new("dup_map")
+                        .short('d')
+                        .long("dup_map")
+                        .value_name("FILE")
+                        .help("write the duplicate and kept IDs to FILE")
+                        .takes_value(true)
+                )
+        )
+        .subcommand(
+            App::new("sort")
+                .about("sort records, inputs larger than --max_mem are sorted in runs spilled to disk")
+                .arg(
+                    Arg::new("key")
+                        .short('k')
+                        .long("key")
+                        .value_name("STR")
+                        .default_value("len")
+                        .possible_values(["len", "id", "natural", "qs"])
+                        .help("sort by length, ID, natural order of IDs (chr1, chr2, ..., chr10, chrX) \
+                            or the qs quality score of -a, FASTA records have the lowest qs")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("reverse")
+                        .short('r')
+                        .long("reverse")
+                        .help("sort in descending order, such as longest first")
+                )
+                .arg(
+                    Arg::new("max_mem")
+                        .short('M')
+                        .long("max_mem")
+                        .value_name("int[G|M|K]")
+                        .default_value("1G")
+                        .help("approximate memory of records sorted in memory")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("tmp_dir")
+                        .short('T')
+                        .long("tmp_dir")
+                        .value_name("DIR")
+                        .help("directory of the temporary files [default: the system temporary directory]")
+                        .takes_value(true)
+                )
+        )
+        .subcommand(
+            App::new("filter")
+                .about("output records passing an expression over the attr fields")
+                .arg(
+                    Arg::new("expr")
+                        .short('e')
+                        .long("expr")
+                        .value_name("STR")
+                        .help("expression of comparisons (== != < <= > >=) between attr fields, numbers and \
+                            quoted strings, combined with && || ! and (), e.g.: 'len >= 1000 && qs >= 10 && \
+                            n_frac < 0.01', a comparison with NA is false, char counts are written as \
+                            count.x, regexes with other chars than [A-Za-z0-9._] are quoted as \
+                            re.\"A{5}\", unknown fields are an error")
+                        .required(true)
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("fail")
+                        .short('f')
+                        .long("fail")
+                        .value_name("FILE")
+                        .help("write the failing records to FILE")
+                        .takes_value(true)
+                )
+        )
         .get_matches();
 
     let paths = args
         .get_many::<String>("input")
         .map_or_else(|| vec!["-"], |v| v.map(|x| x.as_str()).collect());
     if let Some(v) = args.value_of("attr") {
-        attr(&paths, v);
+        attr(
+            &paths,
+            v,
+            args.is_present("header"),
+            args.value_of("format").unwrap().parse::<Format>().unwrap(),
+            args.value_of("threads")
+                .unwrap()
+                .parse::<usize>()
+                .expect("not a valid thread number")
+                .max(1),
+        );
     } else if let Some(v) = args.value_of("reform") {
-        reform(&paths, v);
+        reform(
+            &paths,
+            v,
+            args.value_of("min_len")
+                .unwrap()
+                .parse::<usize>()
+                .expect("not a valid length"),
+            &RenameOpt {
+                id_re: args.value_of("id_re"),
+                id_rep: args.value_of("id_rep").unwrap(),
+                prefix: args.value_of("prefix"),
+                suffix: args.value_of("suffix"),
+                renumber: args.value_of("renumber"),
+                no_des: args.is_present("no_des"),
+                id_map: args.value_of("id_map"),
+                map_out: args.value_of("map_out"),
+            },
+            args.value_of("table")
+                .unwrap()
+                .parse::<usize>()
+                .expect("not a valid genetic code table"),
+            args.value_of("stop").unwrap() == "trim",
+        );
     } else if let Some(v) = args.value_of("split") {
         let raw = v.trim();
         let (num_str, mode) = match raw.chars().last().unwrap() {
@@ -293,15 +685,98 @@ fn main() {
         let step_len = Byte::from_str(subarg.value_of("step_len").unwrap())
             .unwrap()
             .get_bytes() as usize;
+        let hist_bound = |name| {
+            subarg
+                .value_of(name)
+                .map(|v| Byte::from_str(v).unwrap().get_bytes() as u64)
+        };
+        let (hist_min, hist_max) = (hist_bound("hist_min"), hist_bound("hist_max"));
+        if let (Some(s), Some(e)) = (hist_min, hist_max) {
+            if s >= e {
+                panic!("--hist_min must be less than --hist_max");
+            }
+        }
+        let hist_bins = subarg
+            .value_of("hist_bins")
+            .unwrap()
+            .parse::<u64>()
+            .ok()
+            .filter(|x| *x >= 3)
+            .expect("not a valid bin number, must be an integer >= 3");
+        let nx = subarg.value_of("nx").map(|v| {
+            let mut xs = v
+                .split(',')
+                .map(|x| match x.trim().parse::<u32>() {
+                    Ok(x) if (1..=100).contains(&x) => x,
+                    _ => panic!("invalid Nx threshold: {x}, must be an integer in 1-100"),
+                })
+                .collect::<Vec<_>>();
+            xs.sort_unstable();
+            xs.dedup();
+            xs
+        });
         stat(
             &paths,
-            min_len,
-            genome_len,
-            n_len,
-            step_len,
-            subarg.is_present("out_ctg"),
+            &StatOpt {
+                min_len,
+                genome_len,
+                n_len,
+                step_len,
+                out_ctg: subarg.is_present("out_ctg"),
+                per_file: subarg.is_present("per_file"),
+                all: subarg.is_present("all"),
+                format: subarg
+                    .value_of("format")
+                    .unwrap()
+                    .parse::<Format>()
+                    .unwrap(),
+                nx,
+                nx_curve: subarg.is_present("nx_curve"),
+                threads: subarg
+                    .value_of("threads")
+                    .unwrap()
+                    .parse::<usize>()
+                    .expect("not a valid thread number")
+                    .max(1),
+                stream: subarg.is_present("stream"),
+                fai: subarg.is_present("fai"),
+                hist_bins,
+                hist_log: subarg.is_present("hist_log"),
+                hist_min,
+                hist_max,
+                hist_bases: subarg.is_present("hist_bases"),
+            },
         );
     } else if let Some(_subarg) = args.subcommand_matches("diff") {
         diff(&paths);
+    } else if let Some(_subarg) = args.subcommand_matches("validate") {
+        validate(&paths);
+    } else if let Some(subarg) = args.subcommand_matches("dedup") {
+        dedup(
+            &paths,
+            subarg.value_of("mode").unwrap(),
+            subarg.is_present("ignore-case"),
+            subarg.value_of("dup_map"),
+        );
+    } else if let Some(subarg) = args.subcommand_matches("sort") {
+        let max_mem = Byte::from_str(subarg.value_of("max_mem").unwrap())
+            .unwrap()
+            .get_bytes() as usize;
+        let tmp_dir = std::env::temp_dir();
+        sort(
+            &paths,
+            subarg.value_of("key").unwrap(),
+            subarg.is_present("reverse"),
+            max_mem,
+            subarg
+                .value_of("tmp_dir")
+                .unwrap_or_else(|| tmp_dir.to_str().unwrap()),
+        );
+    } else if let Some(subarg) = args.subcommand_matches("filter") {
+        filter(
+            &paths,
+            subarg.value_of("expr").unwrap(),
+            subarg.value_of("fail"),
+        );
     }
 }
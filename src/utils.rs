@@ -1,10 +1,18 @@
 pub mod attr;
+pub mod bgzf;
 pub mod common;
+pub mod dedup;
 pub mod diff;
+pub mod filter;
 pub mod findgap;
 pub mod findseq;
 pub mod getseq;
+pub mod path;
 pub mod reform;
+pub mod rename;
 pub mod sample;
+pub mod sort;
 pub mod split;
 pub mod stat;
+pub mod translate;
+pub mod validate;
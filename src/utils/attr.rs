@@ -1,83 +1,431 @@
-use super::common::parse_fx;
+use super::{
+    common::{parse_fx, reverse_complement_bases, ONT_HEAD_LEN, PHRED_OFFSET, QS_ARRAY},
+    stat::{Format, Row},
+};
 use kseq::record::Fastx;
-use once_cell::sync::Lazy;
+use rayon::{prelude::*, ThreadPoolBuilder};
+use regex::Regex;
+use std::io::{stdout, BufWriter, Write};
 
-static QS_ARRAY:  Lazy<[f64; 256]> = Lazy::new(|| {
-    let mut arr = [0.0; 256];
-    for i in 33..arr.len(){
-        arr[i] = 10_f64.powf(-0.1 * (i - 33) as f64);
-    }
-    arr
-});
+// count of each char, case-insensitive
+fn base_count(seq: &str) -> [usize; 256] {
+    let mut t = [0; 256];
+    seq.bytes()
+        .for_each(|c| t[c.to_ascii_uppercase() as usize] += 1);
+    t
+}
+
+// fraction of the chars in bases, None for an empty sequence
+fn frac(t: &[usize; 256], bases: &[u8], len: usize) -> Option<f64> {
+    let count: usize = bases.iter().map(|c| t[*c as usize]).sum();
+    (len > 0).then(|| count as f64 / len as f64)
+}
 
-fn out_attr(record: Fastx, attr: &str, attr_lower: &str) {
-    let mut index = 0;
-    let mut is_head = true;
+// phred quality at rank i (0-based) in ascending order, chars below the offset are Q0
+fn nth_qual(t: &[usize; 256], mut i: usize) -> usize {
+    for (q, c) in t.iter().enumerate() {
+        if i < *c {
+            return q.saturating_sub(PHRED_OFFSET);
+        }
+        i -= c;
+    }
+    0
+}
 
-    for elm in attr_lower.split(':') {
-        if is_head {
-            is_head = false
+fn longest_homopolymer(seq: &str) -> usize {
+    let seq = seq.as_bytes();
+    let (mut max_len, mut len) = (0, 0);
+    for (i, c) in seq.iter().enumerate() {
+        if i > 0 && c.eq_ignore_ascii_case(&seq[i - 1]) {
+            len += 1;
         } else {
-            print!("\t");
-            index += 1;
+            len = 1;
         }
-        match elm {
-            "id" => print!("{}", record.head()),
-            "len" => print!("{}", record.len()),
-            "qs" => {
-                let qual = record.qual();
-                if !qual.is_empty() {
-                    // \text{read Q} = -10\log_{10}\big[\tfrac{1}{N}\sum 10^{-q_i/10}\big]
-                    let e_sum: f64 = qual
-                        .bytes()
-                        .map(|x| QS_ARRAY[x as usize])
-                        .sum();
-                    print!("{}", -10.0 * (e_sum / record.len() as f64).log10());
-                } else {
-                    print!("NA");
+        max_len = max_len.max(len);
+    }
+    max_len
+}
+
+// Shannon entropy (bits) of the base composition
+fn entropy(t: &[usize; 256], len: usize) -> f64 {
+    t.iter()
+        .filter(|c| **c > 0)
+        .map(|c| {
+            let p = *c as f64 / len as f64;
+            p * (1.0 / p).log2()
+        })
+        .sum()
+}
+
+const DUST_WINDOW: usize = 64;
+
+// mean DUST score of the windows, sum(c_t * (c_t - 1) / 2) / (l - 1) in a window of l triplets
+fn dust(seq: &str) -> f64 {
+    let scores = seq
+        .as_bytes()
+        .chunks(DUST_WINDOW)
+        .filter(|w| w.len() >= 4)
+        .map(dust_window)
+        .collect::<Vec<_>>();
+    if scores.is_empty() {
+        return 0.0;
+    }
+    scores.iter().sum::<f64>() / scores.len() as f64
+}
+
+fn dust_window(seq: &[u8]) -> f64 {
+    let mut t = [0usize; 64];
+    for w in seq.windows(3) {
+        let code = w.iter().try_fold(0, |acc, c| match c.to_ascii_uppercase() {
+            b'A' => Some(acc << 2),
+            b'C' => Some(acc << 2 | 1),
+            b'G' => Some(acc << 2 | 2),
+            b'T' => Some(acc << 2 | 3),
+            _ => None,
+        });
+        if let Some(code) = code {
+            t[code] += 1;
+        }
+    }
+    let score: usize = t.iter().map(|c| c * c.saturating_sub(1) / 2).sum();
+    score as f64 / (seq.len() - 3) as f64
+}
+
+// value of a tag in the description, from a key=value or a SAM-style XX:T:value token
+fn des_tag<'a>(des: &'a str, key: &str) -> Option<&'a str> {
+    des.split_ascii_whitespace().find_map(|token| {
+        if let Some(v) = token.strip_prefix(key).and_then(|x| x.strip_prefix('=')) {
+            return Some(v);
+        }
+        let mut parts = token.splitn(3, ':');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(k), Some(t), Some(v)) if k == key && k.len() == 2 && t.len() == 1 => Some(v),
+            _ => None,
+        }
+    })
+}
+
+// mean quality score of a read, None for FASTA
+pub fn qs(qual: &str) -> Option<f64> {
+    if qual.is_empty() {
+        return None;
+    }
+    // \text{read Q} = -10\log_{10}\big[\tfrac{1}{N}\sum 10^{-q_i/10}\big]
+    let e_sum: f64 = qual.bytes().map(|x| QS_ARRAY[x as usize]).sum();
+    Some(-10.0 * (e_sum / qual.len() as f64).log10())
+}
+
+// the parts of a record used by attr, borrowed from a parsed or a buffered record
+pub struct Rec<'a> {
+    pub head: &'a str,
+    pub des: &'a str,
+    pub seq: &'a str,
+    pub qual: &'a str,
+}
+
+impl<'a> Rec<'a> {
+    pub fn of(record: &'a Fastx) -> Self {
+        Self {
+            head: record.head(),
+            des: record.des(),
+            seq: record.seq(),
+            qual: record.qual(),
+        }
+    }
+}
+
+// value of a field, fixed values are output with 4 decimals
+pub enum Value<'a> {
+    Str(&'a str),
+    Int(usize),
+    Float(f64),
+    Fixed(f64),
+}
+
+impl Value<'_> {
+    pub fn num(&self) -> Option<f64> {
+        match self {
+            Value::Str(v) => v.parse().ok(),
+            Value::Int(v) => Some(*v as f64),
+            Value::Float(v) | Value::Fixed(v) => Some(*v),
+        }
+    }
+}
+
+// char counts shared by the fields of a record
+#[derive(Default)]
+pub struct Cache {
+    bases: Option<[usize; 256]>,
+    quals: Option<[usize; 256]>,
+}
+
+// named fields, other fields are the prefixed ones or char counts
+const NAMES: [&str; 16] = [
+    "id", "des", "index", "len", "gc_frac", "n_frac", "q_min", "q_max", "q_median", "q20", "q30",
+    "hp_max", "entropy", "dust", "qs", "qsont",
+];
+const PREFIXES: [&str; 6] = ["tag.", "motif.", "motif2.", "re.", "re2.", "count."];
+
+// a requested field
+pub struct Field<'a> {
+    key: &'a str,      // name as given, the column name in the header and json
+    elm: String,       // name in lowercase
+    re: Option<Regex>, // pattern of a motif or regex field
+    both: bool,        // count the pattern on both strands
+}
+
+impl<'a> Field<'a> {
+    pub fn new(key: &'a str) -> Self {
+        let elm = key.to_ascii_lowercase();
+        let (mut re, mut both) = (None, false);
+        if let Some((kind, pattern)) = key.split_once('.') {
+            let kind = kind.to_ascii_lowercase();
+            match kind.as_str() {
+                // a motif on either strand is counted on each strand in a separate pass,
+                // but only once if it is its own reverse complement
+                "motif" | "motif2" => {
+                    re = Some(Regex::new(&format!("(?i){}", regex::escape(pattern))).unwrap());
+                    both = kind == "motif2"
+                        && !reverse_complement_bases(pattern).eq_ignore_ascii_case(pattern);
                 }
-            },
-            "qsont" => {
-                let qual = record.qual();
-                let skip_len = if qual.len() >= 60 { 60 } else { 0 };
-                if !qual.is_empty() {
-                    // \text{read Q} = -10\log_{10}\big[\tfrac{1}{N}\sum 10^{-q_i/10}\big]
-                    let e_sum: f64 = qual
-                        .bytes()
-                        .skip(skip_len)
-                        .map(|x|  QS_ARRAY[x as usize])
-                        .sum();
-                    print!("{}", -10.0 * (e_sum / (record.len() - skip_len)as f64).log10());
-                } else {
-                    print!("NA");
+                "re" | "re2" => {
+                    // the regex may be quoted in filter expressions
+                    let pattern = match pattern.as_bytes() {
+                        [q @ (b'"' | b'\''), .., e] if q == e => &pattern[1..pattern.len() - 1],
+                        _ => pattern,
+                    };
+                    re = Some(
+                        Regex::new(pattern)
+                            .unwrap_or_else(|e| panic!("invalid regex {pattern:?}: {e}")),
+                    );
+                    both = kind == "re2";
                 }
-            },
-            "" => (),
-            _ => {
-                let mut t = [0; 256];
-                record
-                    .seq()
-                    .as_bytes()
-                    .iter()
-                    .for_each(|c| t[*c as usize] += 1);
-                let x = &attr[index..index + elm.len()];
-                print!("{}", x.bytes().fold(0, |acc, x| acc + t[x as usize]));
+                _ => (),
             }
         }
-        index += elm.len();
+        Self { key, elm, re, both }
     }
-    if !is_head {
-        println!();
+
+    // a named or prefixed field, false for the bare char counts of attr
+    pub fn is_known(&self) -> bool {
+        NAMES.contains(&self.elm.as_str()) || PREFIXES.iter().any(|x| self.elm.starts_with(x))
+    }
+
+    // value of the field for a record, None for NA
+    pub fn value<'b>(
+        &self,
+        record: &Rec<'b>,
+        record_index: usize,
+        cache: &mut Cache,
+    ) -> Option<Value<'b>> {
+        let len = record.seq.len();
+        if let Some(re) = &self.re {
+            let mut count = re.find_iter(record.seq).count();
+            if self.both {
+                count += re.find_iter(&reverse_complement_bases(record.seq)).count();
+            }
+            return Some(Value::Int(count));
+        }
+        let v = match self.elm.as_str() {
+            "id" => Value::Str(record.head),
+            "des" => Value::Str(record.des.trim_start()),
+            "index" => Value::Int(record_index),
+            "len" => Value::Int(len),
+            "gc_frac" | "n_frac" => {
+                let t = cache.bases.get_or_insert_with(|| base_count(record.seq));
+                let chars: &[u8] = if self.elm == "gc_frac" { b"GC" } else { b"N" };
+                Value::Fixed(frac(t, chars, len)?)
+            }
+            "q_min" | "q_max" | "q_median" | "q20" | "q30" => {
+                if record.qual.is_empty() {
+                    return None;
+                }
+                let t = cache.quals.get_or_insert_with(|| {
+                    let mut t = [0; 256];
+                    record.qual.bytes().for_each(|c| t[c as usize] += 1);
+                    t
+                });
+                Value::Int(match self.elm.as_str() {
+                    "q_min" => nth_qual(t, 0),
+                    "q_max" => nth_qual(t, record.qual.len() - 1),
+                    "q_median" => nth_qual(t, (record.qual.len() - 1) / 2),
+                    "q20" => t[20 + PHRED_OFFSET..].iter().sum(),
+                    _ => t[30 + PHRED_OFFSET..].iter().sum(),
+                })
+            }
+            "hp_max" => Value::Int(longest_homopolymer(record.seq)),
+            "entropy" => {
+                let t = cache.bases.get_or_insert_with(|| base_count(record.seq));
+                Value::Fixed(entropy(t, len))
+            }
+            "dust" => Value::Fixed(dust(record.seq)),
+            "qs" => Value::Float(qs(record.qual)?),
+            "qsont" => {
+                let qual = record.qual;
+                if qual.is_empty() {
+                    return None;
+                }
+                let skip_len = if qual.len() >= ONT_HEAD_LEN {
+                    ONT_HEAD_LEN
+                } else {
+                    0
+                };
+                // \text{read Q} = -10\log_{10}\big[\tfrac{1}{N}\sum 10^{-q_i/10}\big]
+                let e_sum: f64 = qual
+                    .bytes()
+                    .skip(skip_len)
+                    .map(|x| QS_ARRAY[x as usize])
+                    .sum();
+                Value::Float(-10.0 * (e_sum / (len - skip_len) as f64).log10())
+            }
+            "" => Value::Str(""),
+            elm if elm.starts_with("tag.") => Value::Str(des_tag(record.des, &self.key[4..])?),
+            elm => {
+                let chars = if elm.starts_with("count.") {
+                    &self.key[6..]
+                } else {
+                    self.key
+                };
+                let mut t = [0; 256];
+                record.seq.bytes().for_each(|c| t[c as usize] += 1);
+                Value::Int(chars.bytes().fold(0, |acc, x| acc + t[x as usize]))
+            }
+        };
+        Some(v)
     }
 }
 
-pub fn attr(paths: &[&str], attr: &str) {
+fn attr_row(record: &Rec, fields: &[Field], record_index: usize) -> Row {
+    let mut cache = Cache::default();
+    fields.iter().fold(Row::new(), |row, field| {
+        let key = field.key;
+        match field.value(record, record_index, &mut cache) {
+            Some(Value::Str(v)) => row.str(key, v),
+            Some(Value::Int(v)) => row.num(key, v),
+            Some(Value::Float(v)) => row.num(key, v),
+            Some(Value::Fixed(v)) => row.num(key, format!("{v:.4}")),
+            None => row.opt(key, None::<usize>),
+        }
+    })
+}
+
+fn out_row<W: Write>(out: &mut W, row: &Row, format: Format) {
+    let line = if format == Format::Json {
+        row.json()
+    } else {
+        row.tsv()
+    };
+    writeln!(out, "{line}").expect("failed to write result");
+}
+
+// records are buffered in batches, and their rows are made in parallel in the input order
+const BATCH: usize = 10000;
+
+// batch holds the index and (head, des, seq, qual) of records
+fn batch_rows(batch: &[(usize, [String; 4])], fields: &[Field]) -> Vec<Row> {
+    batch
+        .par_iter()
+        .map(|(record_index, [head, des, seq, qual])| {
+            let record = Rec {
+                head,
+                des,
+                seq,
+                qual,
+            };
+            attr_row(&record, fields, *record_index)
+        })
+        .collect()
+}
+
+pub fn attr(paths: &[&str], attr: &str, header: bool, format: Format, threads: usize) {
+    let attr = attr.trim_matches(':');
+    let fields = attr.split(':').map(Field::new).collect::<Vec<_>>();
+    let mut out = BufWriter::new(stdout().lock());
+    if header && format != Format::Json {
+        let head = fields.iter().map(|x| x.key).collect::<Vec<_>>().join("\t");
+        writeln!(out, "{head}").expect("failed to write result");
+    }
+
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .expect("failed to create the thread pool");
+    let mut batch = Vec::with_capacity(BATCH);
     for path in paths {
         let mut records = parse_fx(path);
-        let attr = attr.trim_matches(':');
-        let attr_lower = attr.to_ascii_lowercase();
-        while let Ok(Some(record)) = records.iter_record() {
-            out_attr(record, attr, &attr_lower);
+        while let Ok(Some((record_index, record))) = records.iter_record_index() {
+            if threads == 1 {
+                let row = attr_row(&Rec::of(&record), &fields, record_index);
+                out_row(&mut out, &row, format);
+                continue;
+            }
+            let parts = [record.head(), record.des(), record.seq(), record.qual()];
+            batch.push((record_index, parts.map(String::from)));
+            if batch.len() == BATCH {
+                let rows = pool.install(|| batch_rows(&batch, &fields));
+                rows.iter().for_each(|row| out_row(&mut out, row, format));
+                batch.clear();
+            }
+        }
+        if !batch.is_empty() {
+            let rows = pool.install(|| batch_rows(&batch, &fields));
+            rows.iter().for_each(|row| out_row(&mut out, row, format));
+            batch.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(key: &str, seq: &str) -> usize {
+        count_q(key, seq, "")
+    }
+
+    fn count_q(key: &str, seq: &str, qual: &str) -> usize {
+        let record = Rec {
+            head: "r1",
+            des: "",
+            seq,
+            qual,
+        };
+        match Field::new(key).value(&record, 1, &mut Cache::default()) {
+            Some(Value::Int(v)) => v,
+            _ => unreachable!(),
         }
     }
+
+    #[test]
+    fn motif_on_both_strands() {
+        // ACG and its reverse complement CGT overlap in ACGT
+        assert_eq!(count("motif.ACG", "ACGT"), 1);
+        assert_eq!(count("motif2.ACG", "ACGT"), 2);
+        assert_eq!(count("motif2.acg", "ACGTacgt"), 4);
+        // a reverse-complement palindrome is counted once
+        assert_eq!(count("motif2.ACGT", "ACGTACGT"), 2);
+    }
+
+    #[test]
+    fn quals_below_offset() {
+        assert_eq!(count_q("q_min", "ACG", "  I"), 0);
+        assert_eq!(count_q("q_max", "ACG", "  I"), 40);
+        assert_eq!(count_q("q_median", "ACG", "  I"), 0);
+        // ranks are taken from the quality string
+        assert_eq!(count_q("q_max", "ACGT", "I5"), 40);
+        assert_eq!(count_q("q_median", "ACGT", "I5"), 20);
+    }
+
+    #[test]
+    fn tag_columns_keep_prefix() {
+        let record = Rec {
+            head: "r1",
+            des: " len=5",
+            seq: "ACGT",
+            qual: "",
+        };
+        let fields = [Field::new("len"), Field::new("tag.len")];
+        let row = attr_row(&record, &fields, 1);
+        assert_eq!(row.json(), r#"{"len":4,"tag.len":"5"}"#);
+    }
 }
@@ -0,0 +1,136 @@
+use crossbeam_channel::{bounded, Receiver, Sender};
+use flate2::read::GzDecoder;
+use std::{
+    cmp::min,
+    io::{self, ErrorKind, Read},
+    thread,
+};
+
+type Block = io::Result<Vec<u8>>;
+
+// a gzip header with a 'BC' extra subfield, see the SAM specification
+pub fn is_bgzf(head: &[u8]) -> bool {
+    head.len() >= 16
+        && head[..3] == [0x1f, 0x8b, 8]
+        && head[3] & 4 != 0
+        && head[12..14] == *b"BC"
+        && head[14..16] == [2, 0]
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg)
+}
+
+// read a whole compressed block, None at EOF
+fn read_block<R: Read>(r: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut block = vec![0u8; 12];
+    if r.read(&mut block[..1])? == 0 {
+        return Ok(None);
+    }
+    r.read_exact(&mut block[1..])?;
+    if block[..2] != [0x1f, 0x8b] || block[3] & 4 == 0 {
+        return Err(invalid("not a BGZF block"));
+    }
+    let xlen = u16::from_le_bytes([block[10], block[11]]) as usize;
+    block.resize(12 + xlen, 0);
+    r.read_exact(&mut block[12..])?;
+
+    let mut size = 0;
+    let mut p = 12;
+    while p + 4 <= block.len() {
+        let slen = u16::from_le_bytes([block[p + 2], block[p + 3]]) as usize;
+        if block[p..p + 2] == *b"BC" && slen == 2 && p + 6 <= block.len() {
+            size = u16::from_le_bytes([block[p + 4], block[p + 5]]) as usize + 1;
+        }
+        p += 4 + slen;
+    }
+    if size < block.len() + 8 {
+        return Err(invalid("missing or invalid BGZF block size"));
+    }
+    let start = block.len();
+    block.resize(size, 0);
+    r.read_exact(&mut block[start..])?;
+    Ok(Some(block))
+}
+
+fn inflate(block: &[u8]) -> Block {
+    let n = block.len();
+    let isize = u32::from_le_bytes([block[n - 4], block[n - 3], block[n - 2], block[n - 1]]);
+    let mut out = Vec::with_capacity(isize as usize);
+    GzDecoder::new(block).read_to_end(&mut out)?;
+    Ok(out)
+}
+
+// decompress BGZF blocks with several threads, the output keeps the block order,
+// threads start at the first read
+pub struct ParBgzf {
+    inner: Option<(Box<dyn Read + Send>, usize)>,
+    blocks: Option<Receiver<Receiver<Block>>>,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl ParBgzf {
+    pub fn new(inner: Box<dyn Read + Send>, threads: usize) -> Self {
+        Self {
+            inner: Some((inner, threads)),
+            blocks: None,
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    fn start(&mut self, mut inner: Box<dyn Read + Send>, threads: usize) {
+        let (job_s, job_r) = bounded::<(Vec<u8>, Sender<Block>)>(threads * 4);
+        let (order_s, order_r) = bounded(threads * 8);
+        for _ in 0..threads {
+            let job_r = job_r.clone();
+            thread::spawn(move || {
+                while let Ok((block, s)) = job_r.recv() {
+                    let _ = s.send(inflate(&block));
+                }
+            });
+        }
+
+        // read blocks in order, the receiver of each block is queued before it is inflated
+        thread::spawn(move || loop {
+            let (s, r) = bounded(1);
+            match read_block(&mut inner) {
+                Ok(Some(block)) => {
+                    if order_s.send(r).is_err() || job_s.send((block, s)).is_err() {
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    let _ = s.send(Err(e));
+                    let _ = order_s.send(r);
+                    break;
+                }
+            }
+        });
+        self.blocks = Some(order_r);
+    }
+}
+
+impl Read for ParBgzf {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if let Some((inner, threads)) = self.inner.take() {
+            self.start(inner, threads);
+        }
+        while self.pos == self.buf.len() {
+            let block = match self.blocks.as_ref().and_then(|r| r.recv().ok()) {
+                Some(block) => block,
+                None => return Ok(0),
+            };
+            self.buf = block
+                .recv()
+                .map_err(|_| io::Error::other("BGZF worker exited"))??;
+            self.pos = 0;
+        }
+        let n = min(out.len(), self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
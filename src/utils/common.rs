@@ -1,7 +1,27 @@
-use kseq::{parse_path, parse_reader, record::Fastx, Paths};
+use super::path::open_path;
+use kseq::{
+    parse_reader,
+    record::{Fastx, Result as ParseResult},
+    Paths,
+};
+use once_cell::sync::Lazy;
 use std::io::Cursor;
 use std::path::Path;
 
+pub const PHRED_OFFSET: usize = 33;
+
+// the first bases of ONT reads are usually of low quality
+pub const ONT_HEAD_LEN: usize = 60;
+
+// error probability of each phred+33 quality char
+pub static QS_ARRAY: Lazy<[f64; 256]> = Lazy::new(|| {
+    let mut arr = [0.0; 256];
+    for (i, v) in arr.iter_mut().enumerate().skip(33) {
+        *v = 10_f64.powf(-0.1 * (i - 33) as f64);
+    }
+    arr
+});
+
 const SEQ_COMP_TABLE: [u8; 256] = [
     0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
     26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49,
@@ -64,12 +84,17 @@ pub fn print_seq(seq: &str, reverse: bool, complement: bool) {
 }
 
 pub fn print_fx(r: Fastx, w: usize) {
-    if r.sep().is_empty() {
-        println!(">{} {}", r.head(), r.des());
+    print_parts(r.head(), r.des(), r.seq(), r.sep(), r.qual(), w);
+}
+
+// print a record from its parts, FASTA if sep is empty, wrap lines at w if w > 0
+pub fn print_parts(head: &str, des: &str, seq: &str, sep: &str, qual: &str, w: usize) {
+    if sep.is_empty() {
+        println!(">{head} {des}");
         if w == 0 {
-            println!("{}", r.seq());
+            println!("{seq}");
         } else {
-            let (seq, len) = (r.seq(), r.len());
+            let len = seq.len();
             for i in (0..len).step_by(w) {
                 if i + w < len {
                     println!("{}", &seq[i..i + w]);
@@ -79,11 +104,11 @@ pub fn print_fx(r: Fastx, w: usize) {
             }
         }
     } else {
-        println!("@{} {}", r.head(), r.des());
+        println!("@{head} {des}");
         if w == 0 {
-            println!("{}\n{}\n{}", r.seq(), r.sep(), r.qual());
+            println!("{seq}\n{sep}\n{qual}");
         } else {
-            let (seq, qual, len) = (r.seq(), r.qual(), r.len());
+            let len = seq.len();
             for i in (0..len).step_by(w) {
                 if i + w < len {
                     println!("{}", &seq[i..i + w]);
@@ -91,7 +116,7 @@ pub fn print_fx(r: Fastx, w: usize) {
                     println!("{}", &seq[i..len]);
                 }
             }
-            println!("{}", r.sep());
+            println!("{sep}");
             for i in (0..len).step_by(w) {
                 if i + w < len {
                     println!("{}", &qual[i..i + w]);
@@ -153,17 +178,16 @@ pub fn is_fasta_record(r: &Fastx) -> bool {
 
 pub fn is_fasta_file(path: &str) -> bool {
     let p = path.to_lowercase();
-    if p.ends_with("fasta") || p.ends_with("fa") || p.ends_with("fa.gz") || p.ends_with("fasta.gz")
-    {
+    let p = [".gz", ".zst", ".bz2", ".xz"]
+        .iter()
+        .find_map(|ext| p.strip_suffix(ext))
+        .unwrap_or(&p);
+    if p.ends_with("fasta") || p.ends_with("fa") {
         true
-    } else if p.ends_with("fastq")
-        || p.ends_with("fq")
-        || p.ends_with("fq.gz")
-        || p.ends_with("fastq.gz")
-    {
+    } else if p.ends_with("fastq") || p.ends_with("fq") {
         false
     } else {
-        let mut records = parse_path(path).unwrap();
+        let mut records = parse_fx(path);
         if let Some(record) = records.iter_record().unwrap() {
             return is_fasta_record(&record);
         }
@@ -171,11 +195,46 @@ pub fn is_fasta_file(path: &str) -> bool {
     }
 }
 
-pub fn parse_fx(file: &str) -> Paths<'_> {
-    if !Path::new(file).exists() && file.chars().all(|x| "ATGCNatgcn".contains(x)) {
+// records of the files from an input path, read one file after another
+pub struct FxReader {
+    paths: Vec<Paths<'static>>,
+    index: usize,
+    record: usize, // records read from the current file
+}
+
+impl FxReader {
+    pub fn iter_record(&mut self) -> ParseResult<Option<Fastx<'_>>> {
+        Ok(self.iter_record_index()?.map(|x| x.1))
+    }
+
+    // the next record with its 1-based index in its file, which restarts for each file of a fofn
+    pub fn iter_record_index(&mut self) -> ParseResult<Option<(usize, Fastx<'_>)>> {
+        for paths in self.paths[self.index..].iter_mut() {
+            if let Some(record) = paths.iter_record()? {
+                self.record += 1;
+                return Ok(Some((self.record, record)));
+            }
+            self.index += 1;
+            self.record = 0;
+        }
+        Ok(None)
+    }
+}
+
+pub fn parse_fx(file: &str) -> FxReader {
+    let paths = if !Path::new(file).exists() && file.chars().all(|x| "ATGCNatgcn".contains(x)) {
         let file = format!(">unname\n{file}");
-        parse_reader(Cursor::new(file)).unwrap()
+        vec![parse_reader(Cursor::new(file)).unwrap()]
     } else {
-        parse_path(file).unwrap()
+        // decompress and expand fofn files here, kseq only recognizes gzip
+        open_path(file, 1)
+            .into_iter()
+            .map(|reader| parse_reader(reader).unwrap())
+            .collect()
+    };
+    FxReader {
+        paths,
+        index: 0,
+        record: 0,
     }
 }
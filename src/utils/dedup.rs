@@ -0,0 +1,151 @@
+use super::common::{parse_fx, print_fx, reverse_complement_bases};
+use hashbrown::HashMap;
+use std::{
+    borrow::Cow,
+    collections::hash_map::DefaultHasher,
+    fs::{remove_file, File},
+    hash::{Hash, Hasher},
+    io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write},
+    path::PathBuf,
+};
+
+// 128-bit digest of a key from two seeded hashes, collisions are negligible
+// for billions of records, so only the digests are kept in memory
+fn digest(key: &str) -> u128 {
+    let mut h1 = DefaultHasher::new();
+    key.hash(&mut h1);
+    let mut h2 = DefaultHasher::new();
+    0x9e37_79b9_7f4a_7c15_u64.hash(&mut h2);
+    key.hash(&mut h2);
+    (h1.finish() as u128) << 64 | h2.finish() as u128
+}
+
+// IDs of the kept records for the mapping file, spilled to a temporary file and read back
+// by their offsets, so only the offsets are kept in memory
+struct KeptIds {
+    path: PathBuf,
+    w: BufWriter<File>,
+    r: File,
+    len: u64,     // bytes written
+    flushed: u64, // bytes readable from r
+}
+
+impl KeptIds {
+    fn new() -> Self {
+        let path = std::env::temp_dir().join(format!("fxTools.dedup.{}.tmp", std::process::id()));
+        let w = BufWriter::new(
+            File::create(&path)
+                .unwrap_or_else(|e| panic!("failed to create {}: {e}", path.display())),
+        );
+        let r = File::open(&path).expect("failed to open a temporary file");
+        Self {
+            path,
+            w,
+            r,
+            len: 0,
+            flushed: 0,
+        }
+    }
+
+    // offset of the ID
+    fn push(&mut self, id: &str) -> u64 {
+        writeln!(self.w, "{id}").expect("failed to write a temporary file");
+        self.len += id.len() as u64 + 1;
+        self.len - id.len() as u64 - 1
+    }
+
+    fn get(&mut self, offset: u64) -> String {
+        if offset >= self.flushed {
+            self.w.flush().expect("failed to write a temporary file");
+            self.flushed = self.len;
+        }
+        self.r
+            .seek(SeekFrom::Start(offset))
+            .expect("failed to read a temporary file");
+        let mut id = String::new();
+        BufReader::new(&self.r)
+            .read_line(&mut id)
+            .expect("failed to read a temporary file");
+        id.pop();
+        id
+    }
+}
+
+impl Drop for KeptIds {
+    fn drop(&mut self) {
+        remove_file(&self.path).ok();
+    }
+}
+
+// output the first record of each ID, sequence or sequence with its reverse complement,
+// dropped duplicates are written to map as: duplicate ID, kept ID
+pub fn dedup(paths: &[&str], mode: &str, ignore_case: bool, map: Option<&str>) {
+    let mut map = map.map(|x| {
+        BufWriter::new(File::create(x).unwrap_or_else(|e| panic!("failed to create {x}: {e}")))
+    });
+    let mut seen: HashMap<u128, u64> = HashMap::new();
+    let mut kept_ids = map.is_some().then(KeptIds::new);
+    let (mut total, mut dropped) = (0, 0);
+    for path in paths {
+        let mut records = parse_fx(path);
+        while let Ok(Some(record)) = records.iter_record() {
+            total += 1;
+            let key = match mode {
+                "id" => digest(record.head()),
+                _ => {
+                    let mut seq = Cow::Borrowed(record.seq());
+                    if ignore_case {
+                        seq = Cow::Owned(seq.to_ascii_uppercase());
+                    }
+                    if mode == "rc" {
+                        // the smaller of both strands
+                        let rc = reverse_complement_bases(&seq);
+                        if *rc < *seq {
+                            seq = Cow::Owned(rc);
+                        }
+                    }
+                    digest(&seq)
+                }
+            };
+            if let Some(offset) = seen.get(&key) {
+                dropped += 1;
+                if let (Some(map), Some(kept_ids)) = (map.as_mut(), kept_ids.as_mut()) {
+                    writeln!(map, "{}\t{}", record.head(), kept_ids.get(*offset))
+                        .expect("failed to write the mapping file");
+                }
+                continue;
+            }
+            let offset = kept_ids.as_mut().map_or(0, |x| x.push(record.head()));
+            seen.insert(key, offset);
+            print_fx(record, 0);
+        }
+    }
+    if let Some(mut map) = map {
+        map.flush().expect("failed to write the mapping file");
+    }
+    eprintln!(
+        "kept records: {}\ndropped duplicates: {dropped}",
+        total - dropped
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kept_ids_by_offset() {
+        let mut ids = KeptIds::new();
+        let a = ids.push("a");
+        let b = ids.push("read_b");
+        assert_eq!(ids.get(b), "read_b");
+        let c = ids.push("c");
+        assert_eq!(
+            (ids.get(a), ids.get(c), ids.get(b)),
+            ("a".into(), "c".into(), "read_b".into())
+        );
+        let path = ids.path.clone();
+        drop(ids);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,286 @@
+use super::{
+    attr::{Cache, Field, Rec, Value},
+    common::{parse_fx, print_fx, write_fx},
+};
+use std::{
+    cmp::Ordering,
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Ident(&'a str),
+    Num(f64),
+    Str(String),
+    Op(&'static str),
+}
+
+const OPS: [&str; 12] = [
+    "||", "&&", "==", "!=", "<=", ">=", "<", ">", "!", "(", ")", "=",
+];
+
+fn tokenize(expr: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = expr.trim_start();
+    while !rest.is_empty() {
+        let c = rest.as_bytes()[0];
+        let len = if let Some(op) = OPS.iter().find(|x| rest.starts_with(*x)) {
+            // a single '=' is taken as '=='
+            tokens.push(Token::Op(if *op == "=" { "==" } else { op }));
+            op.len()
+        } else if c == b'"' || c == b'\'' {
+            let end = rest[1..]
+                .find(c as char)
+                .unwrap_or_else(|| panic!("unclosed string in filter expression: {rest}"));
+            tokens.push(Token::Str(rest[1..end + 1].to_string()));
+            end + 2
+        } else if c.is_ascii_digit() || c == b'-' || c == b'.' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || "+-.".contains(c)))
+                .unwrap_or(rest.len());
+            let num = rest[..len].parse().unwrap_or_else(|_| {
+                panic!("invalid number in filter expression: {}", &rest[..len])
+            });
+            tokens.push(Token::Num(num));
+            len
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            let mut len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || "._".contains(c)))
+                .unwrap_or(rest.len());
+            // a quoted regex of re. and re2., such as re."A{5}", is a part of the field
+            let q = rest.as_bytes().get(len).copied().unwrap_or_default();
+            let is_re = ["re.", "re2."].contains(&rest[..len].to_ascii_lowercase().as_str());
+            if is_re && (q == b'"' || q == b'\'') {
+                let end = rest[len + 1..]
+                    .find(q as char)
+                    .unwrap_or_else(|| panic!("unclosed string in filter expression: {rest}"));
+                len += end + 2;
+            }
+            tokens.push(Token::Ident(&rest[..len]));
+            len
+        } else {
+            panic!("unexpected char in filter expression: {rest}");
+        };
+        rest = rest[len..].trim_start();
+    }
+    tokens
+}
+
+enum Operand<'a> {
+    Field(Field<'a>),
+    Num(f64),
+    Str(String),
+}
+
+enum Expr<'a> {
+    Or(Box<Expr<'a>>, Box<Expr<'a>>),
+    And(Box<Expr<'a>>, Box<Expr<'a>>),
+    Not(Box<Expr<'a>>),
+    Cmp(Operand<'a>, &'static str, Operand<'a>),
+}
+
+// recursive descent parser, from the lowest precedence: ||, &&, !, comparisons
+struct Parser<'a> {
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek_op(&self, op: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(Token::Op(x)) if *x == op)
+    }
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let token = self.tokens.get_mut(self.pos)?;
+        self.pos += 1;
+        Some(std::mem::replace(token, Token::Num(0.0)))
+    }
+
+    fn or(&mut self) -> Expr<'a> {
+        let mut expr = self.and();
+        while self.peek_op("||") {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()));
+        }
+        expr
+    }
+
+    fn and(&mut self) -> Expr<'a> {
+        let mut expr = self.not();
+        while self.peek_op("&&") {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.not()));
+        }
+        expr
+    }
+
+    fn not(&mut self) -> Expr<'a> {
+        if self.peek_op("!") {
+            self.pos += 1;
+            return Expr::Not(Box::new(self.not()));
+        }
+        if self.peek_op("(") {
+            self.pos += 1;
+            let expr = self.or();
+            match self.next() {
+                Some(Token::Op(")")) => return expr,
+                x => panic!("expected ')' in filter expression, found {x:?}"),
+            }
+        }
+        let left = self.operand();
+        match self.next() {
+            Some(Token::Op(op)) if ["==", "!=", "<", "<=", ">", ">="].contains(&op) => {
+                Expr::Cmp(left, op, self.operand())
+            }
+            x => panic!("expected a comparison in filter expression, found {x:?}"),
+        }
+    }
+
+    fn operand(&mut self) -> Operand<'a> {
+        match self.next() {
+            Some(Token::Ident(x)) => {
+                let field = Field::new(x);
+                if !field.is_known() {
+                    panic!("unknown field in filter expression: {x}, use count.{x} to count chars");
+                }
+                Operand::Field(field)
+            }
+            Some(Token::Num(x)) => Operand::Num(x),
+            Some(Token::Str(x)) => Operand::Str(x),
+            x => panic!("expected a field, number or string in filter expression, found {x:?}"),
+        }
+    }
+}
+
+fn parse_expr(expr: &str) -> Expr<'_> {
+    let mut parser = Parser {
+        tokens: tokenize(expr),
+        pos: 0,
+    };
+    let parsed = parser.or();
+    if parser.pos < parser.tokens.len() {
+        panic!(
+            "unexpected {:?} in filter expression",
+            parser.tokens[parser.pos]
+        );
+    }
+    parsed
+}
+
+fn operand_value<'b>(
+    operand: &'b Operand,
+    record: &Rec<'b>,
+    record_index: usize,
+    cache: &mut Cache,
+) -> Option<Value<'b>> {
+    match operand {
+        Operand::Field(field) => field.value(record, record_index, cache),
+        Operand::Num(x) => Some(Value::Float(*x)),
+        Operand::Str(x) => Some(Value::Str(x)),
+    }
+}
+
+// strings are compared as strings if both sides are strings, otherwise as numbers,
+// any comparison with NA or a non-numeric string is false
+fn eval<'b>(expr: &'b Expr, record: &Rec<'b>, record_index: usize, cache: &mut Cache) -> bool {
+    match expr {
+        Expr::Or(a, b) => {
+            eval(a, record, record_index, cache) || eval(b, record, record_index, cache)
+        }
+        Expr::And(a, b) => {
+            eval(a, record, record_index, cache) && eval(b, record, record_index, cache)
+        }
+        Expr::Not(a) => !eval(a, record, record_index, cache),
+        Expr::Cmp(a, op, b) => {
+            let a = operand_value(a, record, record_index, cache);
+            let b = operand_value(b, record, record_index, cache);
+            let ord = match (a, b) {
+                (Some(Value::Str(a)), Some(Value::Str(b))) => Some(a.cmp(b)),
+                (Some(a), Some(b)) => a.num().zip(b.num()).and_then(|(a, b)| a.partial_cmp(&b)),
+                _ => None,
+            };
+            ord.is_some_and(|ord| match *op {
+                "==" => ord == Ordering::Equal,
+                "!=" => ord != Ordering::Equal,
+                "<" => ord == Ordering::Less,
+                "<=" => ord != Ordering::Greater,
+                ">" => ord == Ordering::Greater,
+                _ => ord != Ordering::Less,
+            })
+        }
+    }
+}
+
+// print the records passing expr, write the failing records to fail if given
+pub fn filter(paths: &[&str], expr: &str, fail: Option<&str>) {
+    let expr = parse_expr(expr);
+    let mut fail = fail.map(|x| {
+        BufWriter::new(File::create(x).unwrap_or_else(|e| panic!("failed to create {x}: {e}")))
+    });
+    let (mut total, mut passed) = (0, 0);
+    for path in paths {
+        let mut records = parse_fx(path);
+        while let Ok(Some((record_index, record))) = records.iter_record_index() {
+            total += 1;
+            let pass = eval(
+                &expr,
+                &Rec::of(&record),
+                record_index,
+                &mut Cache::default(),
+            );
+            if pass {
+                passed += 1;
+                print_fx(record, 0);
+            } else if let Some(fail) = fail.as_mut() {
+                write_fx(record, 0, fail);
+            }
+        }
+    }
+    if let Some(mut fail) = fail {
+        fail.flush().expect("failed to write result");
+    }
+    eprintln!(
+        "passed records: {passed}\nfailed records: {}",
+        total - passed
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pass(expr: &str, seq: &str) -> bool {
+        let record = Rec {
+            head: "r1",
+            des: "",
+            seq,
+            qual: "",
+        };
+        eval(&parse_expr(expr), &record, 1, &mut Cache::default())
+    }
+
+    #[test]
+    fn known_fields() {
+        assert!(pass("len == 4 && gc_frac >= 0.5", "ACGT"));
+        assert!(pass("count.GC == 2 && re.CG == 1 && !(tag.x == \"y\")", "ACGT"));
+    }
+
+    #[test]
+    fn quoted_regex() {
+        assert!(pass(r#"re."A{3}" == 1 && re.'[AT]+' == 2 && re2."C.G" == 0"#, "AAACTT"));
+        assert!(pass(r#"re2."A{2}" == 2"#, "AAGCTT"));
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown field in filter expression: gc")]
+    fn unknown_field() {
+        parse_expr("gc >= 0.5");
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown field in filter expression: lenn")]
+    fn misspelled_field() {
+        parse_expr("len > 1 && lenn > 3");
+    }
+}
@@ -4,11 +4,14 @@ use regex::Regex;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
 
-fn get_out_info(path: &str) -> HashMap<String, Vec<(u32, u32, bool, bool, String)>> {
+// (start, end, reverse, complement, name)
+type Regions = HashMap<String, Vec<(u32, u32, bool, bool, String)>>;
+
+fn get_out_info(path: &str) -> Regions {
     let mut out_info = HashMap::new();
 
     if let Ok(file) = File::open(path) {
-        for line in BufReader::new(file).lines().flatten() {
+        for line in BufReader::new(file).lines().map_while(Result::ok) {
             if !line.starts_with('#') {
                 let v: Vec<&str> = line.split(char::is_whitespace).collect();
                 let out_info = out_info.entry(v[0].to_owned()).or_insert(vec![]);
@@ -57,7 +60,7 @@ fn read_fai(path: &str) -> Result<HashMap<String, (u32, u64)>, std::io::Error> {
     match File::open(path) {
         Ok(file) => {
             let mut out_info: HashMap<String, (u32, u64)> = HashMap::new();
-            for line in BufReader::new(file).lines().flatten() {
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
                 let v: Vec<&str> = line.split(char::is_whitespace).collect();
                 out_info.insert(
                     v[0].to_owned(),
@@ -114,7 +117,7 @@ fn get_head_reset_region(
 fn getseq_by_index(
     path: &str,
     fai: &HashMap<String, (u32, u64)>,
-    infos: &mut HashMap<String, Vec<(u32, u32, bool, bool, String)>>,
+    infos: &mut Regions,
     reverse: bool,
     complement: bool,
 ) {
@@ -0,0 +1,141 @@
+use super::bgzf::{is_bgzf, ParBgzf};
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use std::{
+    fs::File,
+    io::{stdin, BufRead, BufReader, Cursor, Read},
+    path::Path,
+};
+use xz2::read::XzDecoder;
+
+// compression formats recognized by the magic bytes
+enum Compression {
+    Gz { bgzf: bool },
+    Zstd,
+    Bz2,
+    Xz,
+    Plain,
+}
+
+impl Compression {
+    fn of(head: &[u8]) -> Self {
+        if head.starts_with(b"\x1f\x8b") {
+            Compression::Gz {
+                bgzf: is_bgzf(head),
+            }
+        } else if head.starts_with(b"\x28\xb5\x2f\xfd") {
+            Compression::Zstd
+        } else if head.starts_with(b"BZh") {
+            Compression::Bz2
+        } else if head.starts_with(b"\xfd7zXZ\x00") {
+            Compression::Xz
+        } else {
+            Compression::Plain
+        }
+    }
+}
+
+//convert path to Read, accept auguments: file.txt/file.gz/file.zst/file.bz2/file.xz/file.fofn,
+//BGZF files are decompressed with threads if threads > 1
+pub fn open_path<T>(path: T, threads: usize) -> Vec<Box<dyn Read + Send>>
+where
+    T: AsRef<str>,
+{
+    open_path_named(path, threads)
+        .into_iter()
+        .map(|(_path, reader)| reader)
+        .collect()
+}
+
+// open a single file or stdin, decompressed if needed, never read as a fofn;
+// empty and 1-byte files are fine
+pub fn open_file(path: &str, threads: usize) -> Box<dyn Read + Send> {
+    let mut reader: Box<dyn Read + Send> = match path {
+        "-" => {
+            if atty::is(atty::Stream::Stdin) {
+                panic!("Missing input from Stdin");
+            }
+            Box::new(stdin())
+        }
+        path => Box::new(File::open(path).unwrap_or_else(|_| panic!("Failed open file {path:?}"))),
+    };
+
+    // the first bytes are enough to recognize a compression format and a BGZF header
+    let mut head = Vec::with_capacity(18);
+    (&mut reader)
+        .take(18)
+        .read_to_end(&mut head)
+        .unwrap_or_else(|_| panic!("Failed read file {path:?}"));
+    let compression = Compression::of(&head);
+    reader = Box::new(Cursor::new(head).chain(reader));
+    match compression {
+        Compression::Gz { bgzf: true } if threads > 1 => Box::new(ParBgzf::new(reader, threads)),
+        Compression::Gz { .. } => Box::new(MultiGzDecoder::new(reader)),
+        Compression::Zstd => Box::new(
+            zstd::Decoder::new(reader).unwrap_or_else(|_| panic!("Failed read zstd file {path:?}")),
+        ),
+        Compression::Bz2 => Box::new(MultiBzDecoder::new(reader)),
+        Compression::Xz => Box::new(XzDecoder::new_multi_decoder(reader)),
+        Compression::Plain => reader,
+    }
+}
+
+// the reader of a FASTA/Q file, or the paths listed in a fofn file
+enum Opened {
+    Fastx(Box<dyn Read + Send>),
+    Fofn(Vec<String>),
+}
+
+fn open_or_list(path: &str, threads: usize) -> Opened {
+    let mut reader = open_file(path, threads);
+    let mut format_bytes = [0; 2];
+    reader
+        .read_exact(&mut format_bytes)
+        .unwrap_or_else(|_| panic!("Failed read file {path:?}"));
+    reader = Box::new(Cursor::new(format_bytes.to_vec()).chain(reader));
+    match format_bytes[0] {
+        b'@' | b'>' => Opened::Fastx(reader),
+        _ => {
+            // for a fofn file
+            let reader = BufReader::with_capacity(65536, reader);
+            let parent = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+            let mut paths = Vec::new();
+            for _line in reader.lines().map(|l| l.unwrap()) {
+                let line = _line.trim();
+                if line.starts_with('#') || line.is_empty() {
+                    continue;
+                }
+                let _path = parent.join(line); // convert to a absolute path
+                paths.push(_path.to_string_lossy().into_owned());
+            }
+            Opened::Fofn(paths)
+        }
+    }
+}
+
+// same as open_path, but each reader comes with the path of its file
+pub fn open_path_named<T>(path: T, threads: usize) -> Vec<(String, Box<dyn Read + Send>)>
+where
+    T: AsRef<str>,
+{
+    let path = path.as_ref();
+    match open_or_list(path, threads) {
+        Opened::Fastx(reader) => vec![(path.to_string(), reader)],
+        Opened::Fofn(paths) => paths
+            .iter()
+            .flat_map(|x| open_path_named(x, threads))
+            .collect(),
+    }
+}
+
+// the FASTA/Q files of a path, with the files of a fofn listed in its place, to be opened
+// later with open_path; stdin can be read only once, so it is kept as "-" even for a fofn
+pub fn expand_path(path: &str) -> Vec<String> {
+    if path == "-" {
+        return vec![path.to_string()];
+    }
+    match open_or_list(path, 1) {
+        Opened::Fastx(_) => vec![path.to_string()],
+        Opened::Fofn(paths) => paths.iter().flat_map(|x| expand_path(x)).collect(),
+    }
+}
@@ -1,49 +1,25 @@
-use super::common::{parse_fx, print_fx, print_seq};
+use super::{
+    common::{
+        complement_bases, parse_fx, print_parts, reverse_complement_bases, ONT_HEAD_LEN,
+        PHRED_OFFSET,
+    },
+    rename::{Rename, RenameOpt},
+    translate::{genetic_code, parse_frames, translate_frames},
+};
 use kseq::record::Fastx;
 use owo_colors::OwoColorize;
 use regex::Regex;
+use std::borrow::Cow;
 
-fn lower(r: Fastx) {
-    if r.sep().is_empty() {
-        println!(
-            ">{} {}\n{}",
-            r.head(),
-            r.des(),
-            r.seq().to_ascii_lowercase()
-        );
-    } else {
-        println!(
-            "@{} {}\n{}\n{}\n{}",
-            r.head(),
-            r.des(),
-            r.seq().to_ascii_lowercase(),
-            r.sep(),
-            r.qual()
-        );
-    }
-}
-
-fn upper(r: Fastx) {
-    if r.sep().is_empty() {
-        println!(
-            ">{} {}\n{}",
-            r.head(),
-            r.des(),
-            r.seq().to_ascii_uppercase()
-        );
-    } else {
-        println!(
-            "@{} {}\n{}\n{}\n{}",
-            r.head(),
-            r.des(),
-            r.seq().to_ascii_uppercase(),
-            r.sep(),
-            r.qual()
-        );
+// ID and description of a record after the header operations
+fn names<'b>(rename: &mut Option<Rename>, r: &'b Fastx, path: &str) -> (Cow<'b, str>, &'b str) {
+    match rename {
+        Some(rename) => rename.apply(r.head(), r.des(), path),
+        None => (Cow::Borrowed(r.head()), r.des()),
     }
 }
 
-fn wrapc(path: &str) {
+fn wrapc(path: &str, rename: &mut Option<Rename>) {
     fn out_cbase<T: Into<char>>(b: T, cs: &[i32; 6]) {
         let b = b.into();
         let c: i32 = cs.iter().sum();
@@ -90,7 +66,8 @@ fn wrapc(path: &str) {
     let bases = count_bases(path);
     let mut records = parse_fx(path);
     while let Ok(Some(record)) = records.iter_record() {
-        println!(">{} {}", record.head(), record.des());
+        let (head, des) = names(rename, &record, path);
+        println!(">{head} {des}");
         let (seq, len) = (record.seq().as_bytes(), record.len());
         for i in 0..len {
             out_cbase(seq[i], &bases[i]);
@@ -99,13 +76,13 @@ fn wrapc(path: &str) {
     }
 }
 
-fn split(r: Fastx, re: &Regex) {
+fn split(head: &str, seq: &str, re: &Regex) {
     let mut last_pos = 0;
-    let (seq, len) = (r.seq(), r.len());
+    let len = seq.len();
     for mat in re.find_iter(seq) {
         println!(
             ">{}:{}_{}\n{}",
-            r.head(),
+            head,
             last_pos,
             mat.start() - 1,
             &seq[last_pos..mat.start()]
@@ -114,11 +91,11 @@ fn split(r: Fastx, re: &Regex) {
     }
     if len > last_pos {
         if last_pos == 0 {
-            println!(">{}\n{}", r.head(), &seq[last_pos..len]);
+            println!(">{}\n{}", head, &seq[last_pos..len]);
         } else {
             println!(
                 ">{}:{}_{}\n{}",
-                r.head(),
+                head,
                 last_pos,
                 len - 1,
                 &seq[last_pos..len]
@@ -127,92 +104,346 @@ fn split(r: Fastx, re: &Regex) {
     }
 }
 
-pub fn reform(paths: &[&str], reform: &str) {
-    for path in paths {
-        let mut records = parse_fx(path);
+// trimming modes, each keeps a range of the record
+enum Trim {
+    Qual(usize, usize), // minimum mean quality, window size
+    Crop(usize, usize), // bases to remove from the head and the tail
+    OntHead(usize),     // bases to remove from the head of reads at least as long
+    PolyA(usize),       // minimum length of poly-A tails and poly-T heads
+    N,
+}
 
-        if reform == "lower" {
-            while let Ok(Some(record)) = records.iter_record() {
-                lower(record);
+impl Trim {
+    fn parse(step: &str) -> Option<Self> {
+        let num = |x: &str| -> usize {
+            x.parse()
+                .unwrap_or_else(|_| panic!("invalid number {x:?} in --reform {step}"))
+        };
+        let nums = |x: &str, default: usize| -> (usize, usize) {
+            match x.split_once(',') {
+                Some((a, b)) => (num(a), num(b)),
+                None => (num(x), default),
             }
-        } else if reform == "upper" {
-            while let Ok(Some(record)) = records.iter_record() {
-                upper(record);
+        };
+        if let Some(v) = step.strip_prefix("qtrim") {
+            let (q, w) = nums(v, 4);
+            Some(Trim::Qual(q, w.max(1)))
+        } else if let Some(v) = step.strip_prefix("crop") {
+            let (head, tail) = nums(v, 0);
+            Some(Trim::Crop(head, tail))
+        } else if let Some(v) = step.strip_prefix("onthead") {
+            Some(Trim::OntHead(if v.is_empty() {
+                ONT_HEAD_LEN
+            } else {
+                num(v)
+            }))
+        } else if let Some(v) = step.strip_prefix("polya") {
+            Some(Trim::PolyA(if v.is_empty() { 10 } else { num(v) }.max(1)))
+        } else if step == "trimn" {
+            Some(Trim::N)
+        } else {
+            None
+        }
+    }
+
+    // [beg, end) of the record to keep
+    fn range(&self, seq: &[u8], qual: &[u8]) -> (usize, usize) {
+        let len = seq.len();
+        match *self {
+            // FASTA records have no quality to trim by
+            Trim::Qual(_, _) if qual.is_empty() => (0, len),
+            Trim::Qual(q, w) => {
+                // cut from each end until the mean quality of a window reaches q
+                let w = w.min(len).max(1);
+                let min_sum = q * w;
+                // chars below the phred offset are taken as Q0
+                let phred = |i: usize| (qual[i] as usize).saturating_sub(PHRED_OFFSET);
+                let mut sums = Vec::with_capacity(len + 1 - w);
+                let mut sum: usize = (0..w).map(phred).sum();
+                sums.push(sum);
+                for i in w..len {
+                    sum = sum + phred(i) - phred(i - w);
+                    sums.push(sum);
+                }
+                let Some(mut beg) = sums.iter().position(|x| *x >= min_sum) else {
+                    return (0, 0);
+                };
+                let mut end = sums.iter().rposition(|x| *x >= min_sum).unwrap() + w;
+                // then drop the low-quality bases at the edges of the first and last windows
+                let low = |i: usize| phred(i) < q;
+                while beg < end && low(beg) {
+                    beg += 1;
+                }
+                while end > beg && low(end - 1) {
+                    end -= 1;
+                }
+                (beg, end)
             }
-        } else if reform == "fq2fa" {
-            while let Ok(Some(record)) = records.iter_record() {
-                println!(">{} {}\n{}", record.head(), record.des(), record.seq());
+            Trim::Crop(head, tail) => (head.min(len), len.saturating_sub(tail).max(head.min(len))),
+            // as qsont of attr, shorter reads are kept whole
+            Trim::OntHead(n) => (if len >= n { n } else { 0 }, len),
+            Trim::PolyA(n) => {
+                let head = seq
+                    .iter()
+                    .take_while(|x| **x == b'T' || **x == b't')
+                    .count();
+                let tail = seq
+                    .iter()
+                    .rev()
+                    .take_while(|x| **x == b'A' || **x == b'a')
+                    .count();
+                let beg = if head >= n { head } else { 0 };
+                let end = if tail >= n { len - tail } else { len };
+                (beg, end.max(beg))
             }
-        } else if reform == "fa2fq" {
-            while let Ok(Some(record)) = records.iter_record() {
-                println!(
-                    "@{} {}\n{}\n+\n{}",
-                    record.head(),
-                    record.des(),
-                    record.seq(),
-                    "I".repeat(record.len())
-                );
+            Trim::N => {
+                let head = seq
+                    .iter()
+                    .take_while(|x| **x == b'N' || **x == b'n')
+                    .count();
+                let tail = seq[head..]
+                    .iter()
+                    .rev()
+                    .take_while(|x| **x == b'N' || **x == b'n')
+                    .count();
+                (head, len - tail)
             }
-        } else if reform.starts_with("line") {
-            let is_align = reform.ends_with('c');
-            let w: usize = reform
-                .strip_suffix('c')
-                .unwrap_or(reform)
-                .strip_prefix("line")
-                .unwrap()
-                .parse()
-                .unwrap();
-            if is_align {
-                wrapc(path);
-            } else {
-                while let Ok(Some(record)) = records.iter_record() {
-                    print_fx(record, w);
+        }
+    }
+}
+
+// the sequence and quality passed along the steps, sep and qual are empty for FASTA
+struct Record<'a> {
+    seq: Cow<'a, str>,
+    sep: &'a str,
+    qual: Cow<'a, str>,
+}
+
+// keep [beg, end) of a sequence or quality
+fn cut(s: Cow<str>, beg: usize, end: usize) -> Cow<str> {
+    match s {
+        Cow::Borrowed(s) => Cow::Borrowed(&s[beg..end]),
+        Cow::Owned(mut s) => {
+            s.truncate(end);
+            s.replace_range(..beg, "");
+            Cow::Owned(s)
+        }
+    }
+}
+
+// a step of the chain that changes each record
+enum Step {
+    Lower,
+    Upper,
+    Fq2fa,
+    Fa2fq,
+    Rev(bool, bool), // reverse, complement
+    Trim(Trim),
+}
+
+impl Step {
+    fn parse(step: &str) -> Option<Self> {
+        match step {
+            "lower" => Some(Step::Lower),
+            "upper" => Some(Step::Upper),
+            "fq2fa" => Some(Step::Fq2fa),
+            "fa2fq" => Some(Step::Fa2fq),
+            "rev" => Some(Step::Rev(true, false)),
+            "com" => Some(Step::Rev(false, true)),
+            "rc" => Some(Step::Rev(true, true)),
+            _ => Trim::parse(step).map(Step::Trim),
+        }
+    }
+
+    // None if the record is dropped
+    fn apply<'a>(&self, mut r: Record<'a>, min_len: usize) -> Option<Record<'a>> {
+        match self {
+            Step::Lower => r.seq = Cow::Owned(r.seq.to_ascii_lowercase()),
+            Step::Upper => r.seq = Cow::Owned(r.seq.to_ascii_uppercase()),
+            Step::Fq2fa => (r.sep, r.qual) = ("", Cow::Borrowed("")),
+            Step::Fa2fq => (r.sep, r.qual) = ("+", Cow::Owned("I".repeat(r.seq.len()))),
+            Step::Rev(rev, com) => {
+                r.seq = Cow::Owned(match (rev, com) {
+                    (true, true) => reverse_complement_bases(&r.seq),
+                    (false, true) => complement_bases(&r.seq),
+                    _ => r.seq.chars().rev().collect(),
+                });
+                if *rev && !r.sep.is_empty() {
+                    r.qual = Cow::Owned(r.qual.chars().rev().collect());
                 }
             }
-        } else if reform.starts_with("link") {
-            let w: usize = reform.strip_prefix("link").unwrap().parse().unwrap();
-            println!(">link_reads");
-            let mut is_head = true;
-            while let Ok(Some(record)) = records.iter_record() {
-                if is_head {
-                    is_head = false;
-                    print!("{}", record.seq());
-                } else {
-                    print!("{:N<2$}{}", "", record.seq(), w);
+            Step::Trim(trim) => {
+                let (beg, end) = trim.range(r.seq.as_bytes(), r.qual.as_bytes());
+                if end - beg < min_len {
+                    return None;
+                }
+                r.seq = cut(r.seq, beg, end);
+                if !r.sep.is_empty() {
+                    r.qual = cut(r.qual, beg, end);
                 }
             }
-            println!();
-        } else if reform.starts_with("split") {
-            let w: usize = reform.strip_prefix("split").unwrap().parse().unwrap();
-            if w == 0 {
-                return;
-            };
-            let re = Regex::new(&format!("(?i)N{{{w},}}")).unwrap();
-            while let Ok(Some(record)) = records.iter_record() {
-                split(record, &re);
+        }
+        Some(r)
+    }
+}
+
+// the last step, how records are written
+enum Output {
+    Line(usize),
+    Link(usize),
+    Split(Regex),
+    Trans(Vec<i8>, usize), // frames, line width
+}
+
+// split a chain such as upper,rc,line80, numbers after a comma belong to the step before,
+// as in qtrim20,5
+fn parse_chain(reform: &str) -> Vec<String> {
+    let mut steps: Vec<String> = Vec::new();
+    for step in reform.split(',') {
+        match steps.last_mut() {
+            Some(last) if !step.is_empty() && step.bytes().all(|c| c.is_ascii_digit()) => {
+                last.push(',');
+                last.push_str(step);
             }
-        } else if ["rev", "com", "rc"].contains(&reform) {
-            let (rev, com) = if reform == "rev" {
-                (true, false)
-            } else if reform == "com" {
-                (false, true)
-            } else {
-                (true, true)
+            _ => steps.push(step.to_string()),
+        }
+    }
+    steps
+}
+
+pub fn reform(
+    paths: &[&str],
+    reform: &str,
+    min_len: usize,
+    rename: &RenameOpt,
+    table: usize,
+    trim_stop: bool,
+) {
+    let mut rename = Rename::new(rename);
+    let chain = parse_chain(reform);
+    // the alignment mode reads each input twice, it can not be chained
+    if let Some(w) = chain[0]
+        .strip_prefix("line")
+        .and_then(|x| x.strip_suffix('c'))
+    {
+        assert_eq!(chain.len(), 1, "lineINTc can not be chained in --reform");
+        w.parse::<usize>()
+            .unwrap_or_else(|_| panic!("unknown values: {reform} for --reform"));
+        for path in paths {
+            wrapc(path, &mut rename);
+        }
+        return;
+    }
+
+    let (last, steps) = chain.split_last().unwrap();
+    let width = |x: &str| -> Option<usize> {
+        x.parse()
+            .map(Some)
+            .unwrap_or_else(|_| panic!("unknown values: {reform} for --reform"))
+    };
+    // the output follows the last step, a chain without an output step writes unwrapped records
+    let (steps, output) = if let Some(w) = last.strip_prefix("line") {
+        (steps, Output::Line(width(w).unwrap()))
+    } else if let Some(w) = last.strip_prefix("link") {
+        (steps, Output::Link(width(w).unwrap()))
+    } else if let Some(w) = last.strip_prefix("split") {
+        let w = width(w).unwrap();
+        if w == 0 {
+            return;
+        }
+        let re = Regex::new(&format!("(?i)N{{{w},}}")).unwrap();
+        (steps, Output::Split(re))
+    } else {
+        (&chain[..], Output::Line(0))
+    };
+    // translation writes proteins, it may only be followed by lineINT
+    let (steps, output) = match (steps.split_last(), output) {
+        (Some((last, steps)), Output::Line(w)) if last.starts_with("trans") => {
+            (steps, Output::Trans(parse_frames(&last[5..]), w))
+        }
+        (_, output) => (steps, output),
+    };
+    if let Some(x) = steps.iter().find(|x| x.starts_with("trans")) {
+        panic!("{x} may only be followed by lineINT in --reform");
+    }
+    let code = genetic_code(table);
+    let steps = steps
+        .iter()
+        .map(|x| Step::parse(x).unwrap_or_else(|| panic!("unknown values: {x} for --reform")))
+        .collect::<Vec<_>>();
+    let is_trim = steps.iter().any(|x| matches!(x, Step::Trim(_)));
+
+    let (mut kept, mut dropped) = (0, 0);
+    for path in paths {
+        let mut records = parse_fx(path);
+        if let Output::Link(_) = output {
+            println!(">link_reads");
+        }
+        let mut is_head = true;
+        while let Ok(Some(record)) = records.iter_record() {
+            let mut r = Some(Record {
+                seq: Cow::Borrowed(record.seq()),
+                sep: record.sep(),
+                qual: Cow::Borrowed(record.qual()),
+            });
+            for step in &steps {
+                r = r.and_then(|r| step.apply(r, min_len));
+            }
+            let Some(r) = r else {
+                dropped += 1;
+                continue;
             };
-            while let Ok(Some(record)) = records.iter_record() {
-                if record.sep().is_empty() {
-                    println!(">{} {}", record.head(), record.des());
-                } else {
-                    println!("@{} {}", record.head(), record.des());
+            kept += 1;
+            match &output {
+                Output::Line(w) => {
+                    let (head, des) = names(&mut rename, &record, path);
+                    print_parts(&head, des, &r.seq, r.sep, &r.qual, *w);
+                }
+                Output::Link(w) => {
+                    if is_head {
+                        is_head = false;
+                        print!("{}", r.seq);
+                    } else {
+                        print!("{:N<2$}{}", "", r.seq, w);
+                    }
                 }
-                print_seq(record.seq(), rev, com);
-                if !record.sep().is_empty() {
-                    println!("{}", record.sep());
-                    print_seq(record.qual(), rev, false);
+                Output::Split(re) => {
+                    let (head, _) = names(&mut rename, &record, path);
+                    split(&head, &r.seq, re);
+                }
+                Output::Trans(frames, w) => {
+                    // the mapping table holds the IDs of the frames
+                    let (head, des) = match rename.as_mut() {
+                        Some(rename) => rename.rename(record.head(), record.des(), path),
+                        None => (Cow::Borrowed(record.head()), record.des()),
+                    };
+                    for (frame, protein) in translate_frames(&r.seq, frames, code, trim_stop) {
+                        let id = format!("{head}_f{frame}");
+                        if let Some(rename) = rename.as_mut() {
+                            rename.map(&id, record.head());
+                        }
+                        print_parts(&id, des, &protein, "", "", *w);
+                    }
                 }
             }
-        } else {
-            panic!("unknown values: {reform} for --reform");
         }
+        if let Output::Link(_) = output {
+            println!();
+        }
+    }
+    if is_trim {
+        eprintln!("kept records: {kept}\ndropped records shorter than {min_len}: {dropped}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qtrim_below_offset() {
+        let trim = Trim::Qual(10, 2);
+        assert_eq!(trim.range(b"ACGTAC", b"  II  "), (2, 4));
+        assert_eq!(trim.range(b"ACGT", b"\x1f\x1f\x1f\x1f"), (0, 0));
     }
 }
@@ -0,0 +1,137 @@
+use hashbrown::HashMap;
+use regex::Regex;
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+pub struct RenameOpt<'a> {
+    pub id_re: Option<&'a str>,
+    pub id_rep: &'a str,
+    pub prefix: Option<&'a str>,
+    pub suffix: Option<&'a str>,
+    pub renumber: Option<&'a str>,
+    pub no_des: bool,
+    pub id_map: Option<&'a str>,
+    pub map_out: Option<&'a str>,
+}
+
+// header rewriting, applied in the order: mapping file, regex, renumbering, prefix and suffix
+pub struct Rename<'a> {
+    opt: &'a RenameOpt<'a>,
+    re: Option<Regex>,
+    map: HashMap<String, String>,
+    out: Option<BufWriter<File>>,
+    n: usize, // records renamed so far
+}
+
+// name of an input used by {file}, without the directory and extensions
+fn file_name(path: &str) -> &str {
+    if path == "-" {
+        return "stdin";
+    }
+    let name = Path::new(path)
+        .file_name()
+        .and_then(|x| x.to_str())
+        .unwrap_or(path);
+    name.split('.').next().unwrap_or(name)
+}
+
+impl<'a> Rename<'a> {
+    // None if no header operation is given
+    pub fn new(opt: &'a RenameOpt) -> Option<Self> {
+        if opt.id_re.is_none()
+            && opt.prefix.is_none()
+            && opt.suffix.is_none()
+            && opt.renumber.is_none()
+            && !opt.no_des
+            && opt.id_map.is_none()
+            && opt.map_out.is_none()
+        {
+            return None;
+        }
+        let re = opt
+            .id_re
+            .map(|x| Regex::new(x).unwrap_or_else(|e| panic!("invalid regex {x:?}: {e}")));
+        let mut map = HashMap::new();
+        if let Some(path) = opt.id_map {
+            let file = File::open(path).unwrap_or_else(|e| panic!("failed to open {path}: {e}"));
+            for line in BufReader::new(file).lines() {
+                let line = line.expect("failed to read the mapping file");
+                let mut cols = line.split_whitespace();
+                if let (Some(old), Some(new)) = (cols.next(), cols.next()) {
+                    map.insert(old.to_string(), new.to_string());
+                }
+            }
+        }
+        let out = opt.map_out.map(|x| {
+            BufWriter::new(File::create(x).unwrap_or_else(|e| panic!("failed to create {x}: {e}")))
+        });
+        Some(Self {
+            opt,
+            re,
+            map,
+            out,
+            n: 0,
+        })
+    }
+
+    // new ID and description of a record from the input path, written to the mapping table
+    pub fn apply<'b>(
+        &mut self,
+        head: &'b str,
+        des: &'b str,
+        path: &str,
+    ) -> (Cow<'b, str>, &'b str) {
+        let (id, des) = self.rename(head, des, path);
+        self.map(&id, head);
+        (id, des)
+    }
+
+    // the same as apply, but the caller writes the IDs it outputs with map
+    pub fn rename<'b>(
+        &mut self,
+        head: &'b str,
+        des: &'b str,
+        path: &str,
+    ) -> (Cow<'b, str>, &'b str) {
+        let opt = self.opt;
+        self.n += 1;
+        let mut id = Cow::Borrowed(head);
+        if let Some(new) = self.map.get(head) {
+            id = Cow::Owned(new.clone());
+        }
+        if let Some(re) = &self.re {
+            if let Cow::Owned(x) = re.replace_all(&id, opt.id_rep) {
+                id = Cow::Owned(x);
+            }
+        }
+        if let Some(prefix) = opt.renumber {
+            id = Cow::Owned(format!("{prefix}_{}", self.n));
+        }
+        if opt.prefix.is_some() || opt.suffix.is_some() {
+            let file = file_name(path);
+            let prefix = opt.prefix.unwrap_or_default().replace("{file}", file);
+            let suffix = opt.suffix.unwrap_or_default().replace("{file}", file);
+            id = Cow::Owned(format!("{prefix}{id}{suffix}"));
+        }
+        (id, if opt.no_des { "" } else { des })
+    }
+
+    // write an output ID and the input ID it comes from to the mapping table
+    pub fn map(&mut self, id: &str, head: &str) {
+        if let Some(out) = self.out.as_mut() {
+            writeln!(out, "{id}\t{head}").expect("failed to write the mapping table");
+        }
+    }
+}
+
+impl Drop for Rename<'_> {
+    fn drop(&mut self) {
+        if let Some(out) = self.out.as_mut() {
+            out.flush().expect("failed to write the mapping table");
+        }
+    }
+}
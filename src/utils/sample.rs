@@ -4,7 +4,7 @@ use rand::{Rng, SeedableRng};
 
 pub fn sample(paths: &[&str], fra: f64, seed_opt: Option<u64>) {
     let fraction = fra.clamp(0.0, 1.0); 
-    let base_seed = seed_opt.unwrap_or_else(|| rand::random::<u64>());
+    let base_seed = seed_opt.unwrap_or_else(rand::random::<u64>);
     
     let mut total_bp = 0;
     let mut sampled_reads = 0;
@@ -0,0 +1,283 @@
+use super::{
+    attr::qs,
+    common::{parse_fx, print_parts},
+};
+use std::{
+    cmp::{min, Ordering},
+    fs::{remove_file, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::PathBuf,
+    vec::IntoIter,
+};
+
+// a part of an ID in natural order, numbers sort before text
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Part {
+    Num(u64),
+    Text(String),
+}
+
+// natural order of IDs, such as chr1, chr2, ..., chr10, chrX
+fn natural_key(id: &str) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut rest = id;
+    while !rest.is_empty() {
+        let is_digit = rest.as_bytes()[0].is_ascii_digit();
+        let l = rest
+            .find(|c: char| c.is_ascii_digit() != is_digit)
+            .unwrap_or(rest.len());
+        let part = &rest[..l];
+        parts.push(match part.parse() {
+            Ok(n) if is_digit => Part::Num(n),
+            _ => Part::Text(part.to_string()),
+        });
+        rest = &rest[l..];
+    }
+    parts
+}
+
+enum Key {
+    Len(usize),
+    Id(String),
+    Natural(Vec<Part>),
+    Qs(f64), // -inf for FASTA records
+}
+
+impl Key {
+    fn of(key: &str, r: &Record) -> Self {
+        match key {
+            "len" => Key::Len(r.seq.len()),
+            "id" => Key::Id(r.head.clone()),
+            "natural" => Key::Natural(natural_key(&r.head)),
+            _ => Key::Qs(qs(&r.qual).unwrap_or(f64::NEG_INFINITY)),
+        }
+    }
+
+    fn cmp(&self, other: &Key) -> Ordering {
+        match (self, other) {
+            (Key::Len(a), Key::Len(b)) => a.cmp(b),
+            (Key::Id(a), Key::Id(b)) => a.cmp(b),
+            (Key::Natural(a), Key::Natural(b)) => a.cmp(b),
+            (Key::Qs(a), Key::Qs(b)) => a.total_cmp(b),
+            _ => unreachable!(),
+        }
+    }
+}
+
+// an owned record, sep and qual are empty for FASTA
+struct Record {
+    head: String,
+    des: String,
+    seq: String,
+    sep: String,
+    qual: String,
+}
+
+impl Record {
+    // approximate memory usage
+    fn size(&self) -> usize {
+        self.head.len() + self.des.len() + self.seq.len() + self.sep.len() + self.qual.len() + 160
+    }
+
+    // spilled records are written as 5 lines, so descriptions are kept as they are
+    fn write<W: Write>(&self, w: &mut W) {
+        writeln!(
+            w,
+            "{}\n{}\n{}\n{}\n{}",
+            self.head, self.des, self.seq, self.sep, self.qual
+        )
+        .expect("failed to write a temporary file");
+    }
+
+    fn read<R: BufRead>(r: &mut R) -> Option<Self> {
+        let mut line = || {
+            let mut s = String::new();
+            let n = r
+                .read_line(&mut s)
+                .expect("failed to read a temporary file");
+            s.pop();
+            (n > 0).then_some(s)
+        };
+        Some(Self {
+            head: line()?,
+            des: line()?,
+            seq: line()?,
+            sep: line()?,
+            qual: line()?,
+        })
+    }
+}
+
+// a sorted run, in memory or spilled to a temporary file
+enum Run {
+    Mem(IntoIter<(Key, Record)>),
+    File(BufReader<File>),
+}
+
+impl Run {
+    fn open(file: &PathBuf) -> Self {
+        Run::File(BufReader::new(
+            File::open(file).expect("failed to open a temporary file"),
+        ))
+    }
+
+    fn next(&mut self, key: &str) -> Option<(Key, Record)> {
+        match self {
+            Run::Mem(iter) => iter.next(),
+            Run::File(r) => Record::read(r).map(|x| (Key::of(key, &x), x)),
+        }
+    }
+}
+
+// at most this many runs are merged at once, more spilled runs are merged in passes
+const MAX_FAN_IN: usize = 64;
+
+// spilled runs, removed when dropped, also if sorting panics
+struct TmpFiles {
+    dir: PathBuf,
+    files: Vec<PathBuf>,
+    n: usize, // files created so far
+}
+
+impl TmpFiles {
+    fn create(&mut self) -> BufWriter<File> {
+        let file = self.dir.join(format!(
+            "fxTools.sort.{}.{}.tmp",
+            std::process::id(),
+            self.n
+        ));
+        self.n += 1;
+        let w = BufWriter::new(
+            File::create(&file)
+                .unwrap_or_else(|e| panic!("failed to create {}: {e}", file.display())),
+        );
+        self.files.push(file);
+        w
+    }
+
+    // remove the first n files
+    fn remove(&mut self, n: usize) {
+        for file in self.files.drain(..n) {
+            remove_file(&file)
+                .unwrap_or_else(|e| panic!("failed to remove {}: {e}", file.display()));
+        }
+    }
+}
+
+impl Drop for TmpFiles {
+    fn drop(&mut self) {
+        for file in &self.files {
+            remove_file(file).ok();
+        }
+    }
+}
+
+// k-way merge, ties are taken from the earlier run to keep the input order
+fn merge<F, O>(mut runs: Vec<Run>, key: &str, cmp: F, mut out: O)
+where
+    F: Fn(&Key, &Key) -> Ordering,
+    O: FnMut(Record),
+{
+    let mut heads = runs.iter_mut().map(|x| x.next(key)).collect::<Vec<_>>();
+    loop {
+        let mut min: Option<usize> = None;
+        for (i, x) in heads.iter().enumerate() {
+            if let Some((k, _)) = x {
+                if min.is_none_or(|m| cmp(k, &heads[m].as_ref().unwrap().0) == Ordering::Less) {
+                    min = Some(i);
+                }
+            }
+        }
+        let Some(i) = min else {
+            break;
+        };
+        let (_, r) = std::mem::replace(&mut heads[i], runs[i].next(key)).unwrap();
+        out(r);
+    }
+}
+
+// sort records by key, runs of max_mem bytes are spilled to tmp_dir and merged
+pub fn sort(paths: &[&str], key: &str, reverse: bool, max_mem: usize, tmp_dir: &str) {
+    let cmp = |a: &Key, b: &Key| {
+        if reverse {
+            b.cmp(a)
+        } else {
+            a.cmp(b)
+        }
+    };
+    let mut batch = Vec::new();
+    let mut mem = 0;
+    let mut tmp = TmpFiles {
+        dir: PathBuf::from(tmp_dir),
+        files: Vec::new(),
+        n: 0,
+    };
+    let mut spill = |batch: &mut Vec<(Key, Record)>| {
+        batch.sort_by(|a, b| cmp(&a.0, &b.0));
+        let mut w = tmp.create();
+        batch.drain(..).for_each(|(_, r)| r.write(&mut w));
+        w.flush().expect("failed to write a temporary file");
+    };
+    for path in paths {
+        let mut records = parse_fx(path);
+        while let Ok(Some(record)) = records.iter_record() {
+            let r = Record {
+                head: record.head().to_string(),
+                des: record.des().to_string(),
+                seq: record.seq().to_string(),
+                sep: record.sep().to_string(),
+                qual: record.qual().to_string(),
+            };
+            mem += r.size();
+            batch.push((Key::of(key, &r), r));
+            if mem >= max_mem {
+                spill(&mut batch);
+                mem = 0;
+            }
+        }
+    }
+    batch.sort_by(|a, b| cmp(&a.0, &b.0));
+
+    // merge consecutive spilled runs in passes, keeping their order, until the last merge
+    // with the in-memory run opens at most MAX_FAN_IN runs
+    while tmp.files.len() >= MAX_FAN_IN {
+        let n = tmp.files.len();
+        for i in (0..n).step_by(MAX_FAN_IN) {
+            let runs = tmp.files[i..min(i + MAX_FAN_IN, n)]
+                .iter()
+                .map(Run::open)
+                .collect();
+            let mut w = tmp.create();
+            merge(runs, key, cmp, |r| r.write(&mut w));
+            w.flush().expect("failed to write a temporary file");
+        }
+        tmp.remove(n);
+    }
+    let mut runs = tmp.files.iter().map(Run::open).collect::<Vec<_>>();
+    runs.push(Run::Mem(batch.into_iter()));
+    merge(runs, key, cmp, |r| {
+        print_parts(&r.head, &r.des, &r.seq, &r.sep, &r.qual, 0)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tmp_files_removed_on_drop() {
+        let mut tmp = TmpFiles {
+            dir: std::env::temp_dir(),
+            files: Vec::new(),
+            n: 0,
+        };
+        for _ in 0..3 {
+            tmp.create();
+        }
+        tmp.remove(1);
+        let files = tmp.files.clone();
+        assert!(files.iter().all(|x| x.exists()));
+        drop(tmp);
+        assert!(files.iter().all(|x| !x.exists()));
+    }
+}
@@ -45,7 +45,7 @@ pub fn splits(paths: &[&str], count: usize) {
     }
     create_dir(outdir).unwrap();
 
-    let mut outfiles = creat_outfiles(&outdir, count, is_fasta_file(paths[0]));
+    let mut outfiles = creat_outfiles(outdir, count, is_fasta_file(paths[0]));
 
     for path in paths {
         let mut records = parse_fx(path);
@@ -0,0 +1,141 @@
+use super::report::Row;
+use once_cell::sync::Lazy;
+use std::fmt;
+
+const TYPES: [&str; 7] = ["A", "C", "G", "T", "N", "IUPAC", "Other"];
+const LOWER: usize = 8; // class offset of lowercase (soft-masked) bases
+
+// class of each char: A, C, G, T, N, other IUPAC codes and other chars,
+// plus LOWER if it is in lowercase
+static BASE_CLASS: Lazy<[u8; 256]> = Lazy::new(|| {
+    let mut arr = [6; 256];
+    for c in b'a'..=b'z' {
+        arr[c as usize] = 6 + LOWER as u8;
+    }
+    for (i, c) in b"ACGTN".iter().enumerate() {
+        arr[*c as usize] = i as u8;
+        arr[c.to_ascii_lowercase() as usize] = i as u8 + LOWER as u8;
+    }
+    for c in b"RYSWKMBDHVU" {
+        arr[*c as usize] = 5;
+        arr[c.to_ascii_lowercase() as usize] = 5 + LOWER as u8;
+    }
+    arr
+});
+
+#[derive(Default)]
+pub struct BaseStat {
+    count: [usize; 2 * LOWER],
+    record: [usize; 2 * LOWER], // counts of the record in progress
+}
+
+impl BaseStat {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn add(&mut self, seq: &[u8]) {
+        for c in seq {
+            self.record[BASE_CLASS[*c as usize] as usize] += 1;
+        }
+    }
+
+    // the record in progress ends, keep its counts only if keep
+    pub fn end_record(&mut self, keep: bool) {
+        if keep {
+            for (a, b) in self.count.iter_mut().zip(&self.record) {
+                *a += b;
+            }
+        }
+        self.record = [0; 2 * LOWER];
+    }
+
+    pub fn merge(&mut self, other: &BaseStat) {
+        for (a, b) in self.count.iter_mut().zip(&other.count) {
+            *a += b;
+        }
+    }
+
+    fn get(&self, i: usize) -> usize {
+        self.count[i] + self.count[i + LOWER]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+
+    fn total(&self) -> usize {
+        self.count.iter().sum()
+    }
+
+    fn pct(&self, count: usize) -> f64 {
+        count as f64 * 100.0 / self.total() as f64
+    }
+
+    // GC% of the ACGT bases, None if there is none
+    fn gc_pct(&self) -> Option<f64> {
+        let gc = self.get(1) + self.get(2);
+        let acgt = (0..4).map(|i| self.get(i)).sum::<usize>();
+        (acgt > 0).then(|| gc as f64 * 100.0 / acgt as f64)
+    }
+
+    fn masked(&self) -> usize {
+        self.count[LOWER..].iter().sum()
+    }
+
+    pub fn row(&self, row: Row) -> Row {
+        let v = |f: f64| (self.total() > 0).then(|| format!("{f:.2}"));
+        row.opt("gc_pct", self.gc_pct().map(|x| format!("{x:.2}")))
+            .opt("n_pct", v(self.pct(self.get(4))))
+            .opt("masked_pct", v(self.pct(self.masked())))
+    }
+
+    pub fn count_row(&self, row: Row) -> Row {
+        let row = TYPES.iter().enumerate().fold(row, |row, (i, t)| {
+            row.num(format!("{}_count", t.to_ascii_lowercase()), self.get(i))
+        });
+        self.row(row.num("masked_count", self.masked()))
+    }
+}
+
+impl fmt::Display for BaseStat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let w = self.total().to_string().len().max(9);
+        writeln!(
+            f,
+            "{:<6} {:^w$} {:^11}",
+            "Types", "Count (#)", "Percent (%)"
+        )?;
+        for (i, t) in TYPES.iter().enumerate() {
+            let c = self.get(i);
+            writeln!(f, "{:<6} {:^w$} {:^11.2}", t, c, self.pct(c))?;
+        }
+        let c = self.masked();
+        writeln!(f, "{:<6} {:^w$} {:^11.2}", "Masked", c, self.pct(c))?;
+        let gc_pct = self
+            .gc_pct()
+            .map_or_else(|| "NA".to_string(), |x| format!("{x:.2}"));
+        writeln!(
+            f,
+            "{:<6} {:^w$} {:^11}",
+            "GC",
+            self.get(1) + self.get(2),
+            gc_pct
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gc_pct_without_acgt() {
+        let mut stat = BaseStat::new();
+        stat.add(b"NNNNnn");
+        stat.end_record(true);
+        assert_eq!(stat.gc_pct(), None);
+        assert!(stat.row(Row::new()).json().contains(r#""gc_pct":null"#));
+        assert!(stat.to_string().contains("NA"));
+    }
+}
@@ -0,0 +1,96 @@
+use super::lens::Lens;
+use memchr::memchr2;
+
+// contig and gap lengths, sequences are split at runs of at least n_len Ns
+#[derive(Clone)]
+pub struct GapStat {
+    n_len: u64,
+    pub ctg_lens: Lens,
+    pub ctg_total: u64,
+    pub gap_lens: Lens,
+    pub gap_total: u64,
+    run: u64,                // length of the N run in progress
+    ctg: u64,                // length of the contig in progress, without the N run
+    parts: Vec<(bool, u64)>, // (is gap, length) of the record in progress
+}
+
+impl GapStat {
+    pub fn new(n_len: usize, stream: bool) -> Self {
+        Self {
+            n_len: n_len as u64,
+            ctg_lens: Lens::new(stream),
+            ctg_total: 0,
+            gap_lens: Lens::new(stream),
+            gap_total: 0,
+            run: 0,
+            ctg: 0,
+            parts: Vec::new(),
+        }
+    }
+
+    // a segment of the record in progress, N runs may span segments
+    pub fn add(&mut self, seq: &[u8]) {
+        let mut pos = 0;
+        while pos < seq.len() {
+            let n = seq[pos..]
+                .iter()
+                .take_while(|c| **c == b'N' || **c == b'n')
+                .count();
+            self.run += n as u64;
+            pos += n;
+            if pos < seq.len() {
+                let l = memchr2(b'N', b'n', &seq[pos..]).unwrap_or(seq.len() - pos);
+                self.end_run();
+                self.ctg += l as u64;
+                pos += l;
+            }
+        }
+    }
+
+    fn end_run(&mut self) {
+        if self.run > 0 && self.run >= self.n_len {
+            if self.ctg > 0 {
+                self.parts.push((false, self.ctg));
+            }
+            self.parts.push((true, self.run));
+            self.ctg = 0;
+        } else {
+            self.ctg += self.run;
+        }
+        self.run = 0;
+    }
+
+    // contigs and gaps of the record in progress, in order
+    pub fn parts(&mut self) -> &[(bool, u64)] {
+        self.end_run();
+        if self.ctg > 0 {
+            self.parts.push((false, self.ctg));
+            self.ctg = 0;
+        }
+        &self.parts
+    }
+
+    // the record in progress ends, keep its contigs and gaps only if keep
+    pub fn end_record(&mut self, keep: bool) {
+        self.parts();
+        if keep {
+            for (is_gap, len) in &self.parts {
+                if *is_gap {
+                    self.gap_lens.push(*len);
+                    self.gap_total += len;
+                } else {
+                    self.ctg_lens.push(*len);
+                    self.ctg_total += len;
+                }
+            }
+        }
+        self.parts.clear();
+    }
+
+    pub fn merge(&mut self, other: GapStat) {
+        self.ctg_lens.append(other.ctg_lens);
+        self.ctg_total += other.ctg_total;
+        self.gap_lens.append(other.gap_lens);
+        self.gap_total += other.gap_total;
+    }
+}
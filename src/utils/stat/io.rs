@@ -3,7 +3,10 @@ use memchr::{
     // memchr3,
     memchr_iter,
 };
-use std::io::{self, ErrorKind};
+use std::{
+    cmp::min,
+    io::{self, ErrorKind},
+};
 
 const NEWLINE: u8 = b'\n';
 // const GAPUPPER: u8 = b'N';
@@ -79,6 +82,12 @@ impl Buffer {
         None
     }
 
+    pub fn next_line(&mut self) -> Option<(&[u8], bool)> {
+        let start = self.pos;
+        self.next_line_len()
+            .map(|(p, a)| (&self.buf[start..start + p], a))
+    }
+
     pub fn skip_newlines(&mut self) -> usize {
         let mut skip_len = 0;
         while self.pos < self.buf_len && self.buf[self.pos] == NEWLINE {
@@ -129,6 +138,29 @@ impl Buffer {
         skip_len
     }
 
+    // same as skip_bases, but pass each skipped line segment to f
+    pub fn skip_bases_with<F: FnMut(&[u8])>(&mut self, len: usize, mut f: F) -> usize {
+        let mut skip_len = 0;
+        while skip_len < len && self.pos < self.buf_len {
+            let end = min(self.buf_len, self.pos + len - skip_len);
+            match memchr(NEWLINE, &self.buf[self.pos..end]) {
+                Some(p) => {
+                    f(&self.buf[self.pos..self.pos + p]);
+                    skip_len += p;
+                    self.pos += p + 1;
+                }
+                None => {
+                    f(&self.buf[self.pos..end]);
+                    skip_len += end - self.pos;
+                    self.pos = end;
+                }
+            }
+        }
+
+        self.skip_newlines();
+        skip_len
+    }
+
     // pub fn skip_ns(&mut self) -> usize {
     //     let mut skip_n = 0;
     //     while self.buf[self.pos] == GAPUPPER || self.buf[self.pos] == GAPLOWER {
@@ -0,0 +1,143 @@
+use rayon::prelude::*;
+
+const EXACT_CAP: u64 = 1 << 20; // lengths below are counted exactly
+const SUB_BINS: u32 = 64; // log bins per doubling above EXACT_CAP, ~1.6% wide
+const LOG_BINS: usize = ((64 - EXACT_CAP.trailing_zeros()) * SUB_BINS) as usize;
+
+// (length, count) in ascending order of length
+pub type Runs = Vec<(u64, u64)>;
+
+// length counts in constant memory, a log bin is reported at the mean length of its sequences
+#[derive(Clone)]
+pub struct LenCount {
+    exact: Vec<u64>,        // count of each length, grown up to EXACT_CAP
+    bins: Vec<(u64, u128)>, // (count, total length) of each log bin
+}
+
+impl LenCount {
+    fn new() -> Self {
+        Self {
+            exact: Vec::new(),
+            bins: vec![(0, 0); LOG_BINS],
+        }
+    }
+
+    fn bin(len: u64) -> usize {
+        let e = 63 - len.leading_zeros();
+        let sub = (len >> (e - SUB_BINS.trailing_zeros())) as u32 & (SUB_BINS - 1);
+        ((e - EXACT_CAP.trailing_zeros()) * SUB_BINS + sub) as usize
+    }
+
+    fn add(&mut self, len: u64, count: u64) {
+        if len < EXACT_CAP {
+            let i = len as usize;
+            if i >= self.exact.len() {
+                self.exact.resize(i + 1, 0);
+            }
+            self.exact[i] += count;
+        } else {
+            let bin = &mut self.bins[Self::bin(len)];
+            bin.0 += count;
+            bin.1 += len as u128 * count as u128;
+        }
+    }
+
+    fn merge(&mut self, other: &LenCount) {
+        if other.exact.len() > self.exact.len() {
+            self.exact.resize(other.exact.len(), 0);
+        }
+        self.exact
+            .iter_mut()
+            .zip(&other.exact)
+            .for_each(|(a, b)| *a += b);
+        self.bins.iter_mut().zip(&other.bins).for_each(|(a, b)| {
+            a.0 += b.0;
+            a.1 += b.1;
+        });
+    }
+
+    fn runs(&self) -> Runs {
+        let exact = self
+            .exact
+            .iter()
+            .enumerate()
+            .filter(|(_len, c)| **c > 0)
+            .map(|(len, c)| (len as u64, *c));
+        let bins = self
+            .bins
+            .iter()
+            .filter(|(c, _total)| *c > 0)
+            .map(|(c, total)| ((total / *c as u128) as u64, *c));
+        exact.chain(bins).collect()
+    }
+}
+
+// sequence lengths, all kept or counted in constant memory
+#[derive(Clone)]
+pub enum Lens {
+    All(Vec<u64>),
+    Count(LenCount),
+}
+
+impl Lens {
+    pub fn new(stream: bool) -> Self {
+        if stream {
+            Lens::Count(LenCount::new())
+        } else {
+            Lens::All(Vec::new())
+        }
+    }
+
+    pub fn push(&mut self, len: u64) {
+        match self {
+            Lens::All(lens) => lens.push(len),
+            Lens::Count(counts) => counts.add(len, 1),
+        }
+    }
+
+    pub fn append(&mut self, other: Lens) {
+        match (self, other) {
+            (Lens::All(lens), Lens::All(other)) => {
+                if lens.is_empty() {
+                    *lens = other;
+                } else {
+                    lens.extend(other);
+                }
+            }
+            (Lens::Count(counts), Lens::Count(other)) => counts.merge(&other),
+            _ => unreachable!("can not merge kept and counted lengths"),
+        }
+    }
+
+    pub fn runs(&mut self) -> Runs {
+        match self {
+            Lens::All(lens) => {
+                lens.par_sort_unstable();
+                let mut runs: Runs = Vec::new();
+                for len in lens.iter() {
+                    match runs.last_mut() {
+                        Some((l, c)) if l == len => *c += 1,
+                        _ => runs.push((*len, 1)),
+                    }
+                }
+                runs
+            }
+            Lens::Count(counts) => counts.runs(),
+        }
+    }
+}
+
+pub fn count(runs: &[(u64, u64)]) -> u64 {
+    runs.iter().map(|x| x.1).sum()
+}
+
+// the length at rank i (0-based) in ascending order
+pub fn nth(runs: &[(u64, u64)], mut i: u64) -> u64 {
+    for (len, c) in runs {
+        if i < *c {
+            return *len;
+        }
+        i -= c;
+    }
+    runs.last().map_or(0, |x| x.0)
+}
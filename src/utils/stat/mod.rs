@@ -1,31 +1,77 @@
-use super::common::parse_fx;
+use super::{
+    common::parse_fx,
+    path::{expand_path, open_path},
+};
 use crossbeam_channel::{bounded, unbounded};
 use crossbeam_utils::thread;
 use rayon::prelude::*;
-use regex::Regex;
-use std::{cmp::max, fmt, fs::File, io::Write};
+use std::{
+    cmp::{max, min},
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+};
 
+mod base;
+mod gap;
 mod io;
-mod path;
+mod lens;
+mod qual;
+mod report;
+use base::BaseStat;
+use gap::GapStat;
 use io::Buffer;
-use path::open_path;
+use lens::{Lens, Runs};
+use qual::QualStat;
+use report::Cell;
+pub use report::{Format, Row};
 
 const BUF_COUNT: usize = 2;
 
-#[derive(Default)]
+const NX_DEFAULT: [u32; 9] = [10, 20, 30, 40, 50, 60, 70, 80, 90];
+const NX_FILE: [u32; 2] = [50, 90]; // for the per-file rows
+
 struct Nx {
-    count: [usize; 10],
-    len: [u32; 10],
+    xs: Vec<u32>,
+    count: Vec<u64>, // Lx
+    len: Vec<u64>,
+    g_count: Vec<u64>, // LGx, empty if the genome size is unknown
+    g_len: Vec<u64>,
+    aun: f64,
+    aung: f64,
 }
 
 impl Nx {
-    fn new() -> Self {
-        Default::default()
+    fn new(xs: &[u32]) -> Self {
+        Self {
+            xs: xs.to_vec(),
+            count: vec![0; xs.len()],
+            len: vec![0; xs.len()],
+            g_count: Vec::new(),
+            g_len: Vec::new(),
+            aun: 0.0,
+            aung: 0.0,
+        }
+    }
+
+    fn is_ng(&self) -> bool {
+        !self.g_len.is_empty()
+    }
+
+    // (LGx, NGx) at the i-th threshold, None if the sequences can not reach it
+    fn ng(&self, i: usize) -> Option<(u64, u64)> {
+        (self.g_count[i] > 0).then(|| (self.g_count[i], self.g_len[i]))
     }
 
     fn get_width(&self) -> (usize, usize) {
-        let mut w1 = self.count[8].to_string().len();
-        let mut w2 = self.len[0].to_string().len();
+        let mut w1 = max(self.count.last(), self.g_count.last())
+            .unwrap_or(&0)
+            .to_string()
+            .len();
+        let mut w2 = max(self.len.first(), self.g_len.first())
+            .unwrap_or(&0)
+            .to_string()
+            .len();
         if w1 < 9 {
             w1 = 9;
         }
@@ -35,20 +81,48 @@ impl Nx {
         (w1, w2)
     }
 
-    fn fill(mut self, lens: &[u32], total: usize) -> Self {
-        let mut i = 0;
-        let mut acc = 0;
-        for len in lens.iter().rev() {
-            acc += *len as usize;
-            self.count[i] += 1;
-            while acc as f64 > ((i + 1) * total) as f64 * 0.1 {
-                self.len[i] = *len;
+    // NGx and LGx are filled if genome_len > 0
+    fn fill(mut self, runs: &[(u64, u64)], total: u64, genome_len: u64) -> Self {
+        if genome_len > 0 {
+            self.g_count.resize(self.xs.len(), 0);
+            self.g_len.resize(self.xs.len(), 0);
+        }
+        // the number of sequences of a run needed to exceed x% of base, or to reach all of it for x = 100
+        let reach = |acc: u64, len: u64, c: u64, x: u32, base: u64| {
+            let target = (x as u64 * base).saturating_sub((x == 100) as u64);
+            let need = target.saturating_sub(acc * 100);
+            ((acc + len * c) * 100 > target).then(|| need / max(len * 100, 1) + 1)
+        };
+        let (mut i, mut j) = (0, 0);
+        let (mut n, mut acc) = (0, 0);
+        let mut sq: u128 = 0;
+        for &(len, c) in runs.iter().rev() {
+            while let Some(k) = (i < self.xs.len())
+                .then(|| reach(acc, len, c, self.xs[i], total))
+                .flatten()
+            {
+                self.count[i] = n + k;
+                self.len[i] = len;
                 i += 1;
-                if i >= 10 {
-                    break;
-                }
-                self.count[i] += self.count[i - 1];
             }
+            while let Some(k) = (genome_len > 0 && j < self.xs.len())
+                .then(|| reach(acc, len, c, self.xs[j], genome_len))
+                .flatten()
+            {
+                self.g_count[j] = n + k;
+                self.g_len[j] = len;
+                j += 1;
+            }
+            n += c;
+            acc += len * c;
+            sq += (len as u128).pow(2) * c as u128;
+        }
+        // area under the Nx curve
+        if total > 0 {
+            self.aun = sq as f64 / total as f64;
+        }
+        if genome_len > 0 {
+            self.aung = sq as f64 / genome_len as f64;
         }
         self
     }
@@ -57,172 +131,285 @@ impl Nx {
 impl fmt::Display for Nx {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let (w1, w2) = self.get_width();
-        writeln!(
+        write!(
             f,
             "{:<5} {:^w1$} {:^w2$}",
             "Types", "Count (#)", "Length (bp)",
         )?;
-        for i in 0..9 {
-            writeln!(
-                f,
-                "N{:<4} {:^w1$} {:^w2$}",
-                (i + 1) * 10,
-                self.count[i],
-                self.len[i],
-            )?;
+        if self.is_ng() {
+            write!(f, " {:^w1$} {:^w2$}", "LGx (#)", "NGx (bp)")?;
+        }
+        writeln!(f)?;
+        for (i, x) in self.xs.iter().enumerate() {
+            write!(f, "N{:<4} {:^w1$} {:^w2$}", x, self.count[i], self.len[i])?;
+            if self.is_ng() {
+                match self.ng(i) {
+                    Some((count, len)) => write!(f, " {count:^w1$} {len:^w2$}")?,
+                    None => write!(f, " {:^w1$} {:^w2$}", "-", "-")?,
+                }
+            }
+            writeln!(f)?;
         }
         Ok(())
     }
 }
 
-const BIN_LEN: u32 = 30;
-const UNIT_BIN: usize = 200;
+const UNIT_BIN: u64 = 200;
 struct His {
-    pw: usize,   // pos width
-    cw: usize,   // count width
-    unit: usize, // count of a '*'
-    step: u32,
-    start: u32,
-    min: u32,
-    max: u32,
-    count: [usize; BIN_LEN as usize],
+    pw: usize,        // pos width
+    cw: usize,        // count width
+    unit: u64,        // count of a '*'
+    weighted: bool,   // count bases instead of sequences
+    bounds: Vec<u64>, // bin i holds [bounds[i - 1], bounds[i]), the first and last bins hold the rest
+    min: u64,
+    max: u64,
+    count: Vec<u64>,
 }
 
 impl His {
-    fn new(s: u32, e: u32, c: usize) -> Self {
-        let step = max((e - s) / (BIN_LEN - 2), 1);
+    // bounds of n linear bins from s to e, the last one is e; if round, the bins of the
+    // default histogram are used, with s rounded down to an integer step
+    fn linear(s: u64, e: u64, n: u64, round: bool) -> Vec<u64> {
+        if round {
+            let step = max(e.saturating_sub(s) / (n - 2), 1);
+            return (0..n - 1).map(|i| s / step * step + step * i).collect();
+        }
+        let mut bounds: Vec<u64> = Vec::new();
+        for i in 0..n - 1 {
+            let bound = s + e.saturating_sub(s) * i / (n - 2);
+            if bounds.last().is_none_or(|x| bound > *x) {
+                bounds.push(bound);
+            }
+        }
+        bounds
+    }
+
+    // bounds of n log10-scaled bins from s to e
+    fn log(s: u64, e: u64, n: u64) -> Vec<u64> {
+        let (s, e) = ((max(s, 1) as f64).log10(), (max(e, 1) as f64).log10());
+        let mut bounds: Vec<u64> = Vec::new();
+        for i in 0..n - 1 {
+            let bound = 10_f64.powf(s + (e - s) * i as f64 / (n - 2) as f64).round() as u64;
+            if bounds.last().is_none_or(|x| bound > *x) {
+                bounds.push(bound);
+            }
+        }
+        bounds
+    }
+
+    // c is the total count of sequences, or of bases if weighted
+    fn new(bounds: Vec<u64>, c: u64, weighted: bool) -> Self {
         Self {
-            pw: e.to_string().len(),
+            pw: bounds.last().unwrap_or(&0).to_string().len(),
             cw: c.to_string().len(),
             unit: max(c / UNIT_BIN, 5),
-            step,
-            start: s / step * step,
-            min: u32::MIN,
-            max: u32::MAX,
-            count: [0; BIN_LEN as usize],
+            weighted,
+            count: vec![0; bounds.len() + 1],
+            bounds,
+            min: u64::MIN,
+            max: u64::MAX,
         }
     }
 
-    fn fill(mut self, lens: &[u32]) -> Self {
-        if !lens.is_empty() {
+    fn fill(mut self, runs: &[(u64, u64)]) -> Self {
+        if !runs.is_empty() {
             let mut idx: usize = 0;
-            let mut max_len = self.start;
-            for len in lens {
-                while idx + 1 < BIN_LEN as usize && *len >= max_len {
+            for (len, c) in runs {
+                while idx < self.bounds.len() && *len >= self.bounds[idx] {
                     idx += 1;
-                    max_len += self.step;
                 }
-                self.count[idx] += 1;
+                self.count[idx] += if self.weighted { len * c } else { *c };
             }
-            self.min = lens[0];
-            self.max = *lens.last().unwrap();
+            self.min = runs[0].0;
+            self.max = runs[runs.len() - 1].0;
             self.pw = self.max.to_string().len();
         }
         self
     }
-}
 
-impl fmt::Display for His {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "[length histogram ('*' =~ {} reads)]", self.unit)?;
-        for (p, v) in self.count.into_iter().enumerate() {
-            let p = p as u32;
-            if v > 0 {
-                writeln!(
-                    f,
-                    "{:>pw$} {:>pw$} {:>cw$} {:*>cv$}",
-                    if p == 0 {
-                        self.min
-                    } else {
-                        self.start + self.step * (p - 1)
-                    },
-                    if p == BIN_LEN - 1 {
+    fn unit_name(&self) -> &'static str {
+        if self.weighted {
+            "bases"
+        } else {
+            "reads"
+        }
+    }
+
+    // non-empty bins: (start, end, count)
+    fn bins(&self) -> Vec<(u64, u64, u64)> {
+        self.count
+            .iter()
+            .enumerate()
+            .filter(|(_p, v)| **v > 0)
+            .map(|(p, v)| {
+                (
+                    if p == 0 { self.min } else { self.bounds[p - 1] },
+                    if p == self.bounds.len() {
                         self.max
                     } else {
-                        self.start + self.step * p - 1
+                        self.bounds[p] - 1
                     },
-                    v,
-                    "",
-                    pw = self.pw,
-                    cw = self.cw,
-                    cv = v / self.unit
-                )?;
-            }
+                    *v,
+                )
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for His {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "[length histogram ('*' =~ {} {})]",
+            self.unit,
+            self.unit_name()
+        )?;
+        for (s, e, v) in self.bins() {
+            writeln!(
+                f,
+                "{:>pw$} {:>pw$} {:>cw$} {:*>cv$}",
+                s,
+                e,
+                v,
+                "",
+                pw = self.pw,
+                cw = self.cw,
+                cv = (v / self.unit) as usize
+            )?;
         }
         Ok(())
     }
 }
 
-fn out_step(lens: &[u32], step_len: u32, genome_len: usize) {
+fn out_step(runs: &[(u64, u64)], opt: &StatOpt) {
+    let (step_len, genome_len) = (opt.step_len as u64, opt.genome_len as u64);
     thread::scope(|work| {
         let (in_s, in_r) = bounded(1024);
         // input thread
         work.spawn(move |_| {
             let mut step = 0;
-            for (i, len) in lens.iter().enumerate(){
-                while *len >= step + step_len{
+            for (i, (len, _c)) in runs.iter().enumerate() {
+                while *len >= step + step_len {
                     step += step_len;
                 }
 
                 if *len >= step {
-                    in_s.send((step, &lens[i..])).unwrap();
+                    in_s.send((step, &runs[i..])).unwrap();
                     step += step_len;
                 }
             }
         });
 
         //work thread
-        let mut nxs = work.spawn(move |_| {
-            let work_thread = 2;
-            thread::scope(|scoped| {
-                let mut handles = Vec::with_capacity(work_thread);
-                for _i in 0..work_thread {
-                    let in_r = in_r.clone();
-                    let handle = scoped.spawn(move |_| {
-                        let mut nxs = Vec::new();
-                        while let Ok((step, lens)) = in_r.recv() {
-                            let total = lens.iter().sum::<u32>();
-                           let nx = Nx::new().fill(lens, if genome_len > 0 { genome_len } else { total as usize });
-                           nxs.push((step, lens.len(), total, nx.count[4], nx.len[4]));
-                        }
-                        nxs
-                    });
-                    handles.push(handle);
-                }
+        let mut nxs = work
+            .spawn(move |_| {
+                let work_thread = 2;
+                thread::scope(|scoped| {
+                    let mut handles = Vec::with_capacity(work_thread);
+                    for _i in 0..work_thread {
+                        let in_r = in_r.clone();
+                        let handle = scoped.spawn(move |_| {
+                            let mut nxs = Vec::new();
+                            while let Ok((step, runs)) = in_r.recv() {
+                                let total = runs.iter().map(|(len, c)| len * c).sum::<u64>();
+                                let nx = Nx::new(&[50]).fill(runs, total, genome_len);
+                                nxs.push((step, lens::count(runs), total, nx));
+                            }
+                            nxs
+                        });
+                        handles.push(handle);
+                    }
 
-                let mut nxs: Vec<(u32, usize, u32, usize, u32)> = Vec::new();
-                for res in handles.into_iter().map(|h| h.join().unwrap()){
-                    nxs.extend(res);
-                }
-                nxs
-            }).unwrap()
-        }).join().unwrap();
+                    let mut nxs: Vec<(u64, u64, u64, Nx)> = Vec::new();
+                    for res in handles.into_iter().map(|h| h.join().unwrap()) {
+                        nxs.extend(res);
+                    }
+                    nxs
+                })
+                .unwrap()
+            })
+            .join()
+            .unwrap();
 
-        if !nxs.is_empty(){
-            // sort by step
-            nxs.sort_unstable_by_key(|k| k.0);
+        // sort by step
+        nxs.sort_unstable_by_key(|k| k.0);
+        if opt.format != Format::Table {
+            report::out_step(opt.format, &nxs);
+        } else if !nxs.is_empty(){
             let w0 = max(nxs.last().unwrap().0.to_string().len(), 5) + 2; //safe unwrap
-            let w1 = max(nxs[0].3.to_string().len(), 9);
-            let w2 = max(nxs[0].4.to_string().len(), 10);
-            println!("{:<w$} {:^w1$} {:^w2$} {:^w1$} {:^w2$}", "Types", "Count", "Length", "N50 Count", "N50 Length", w = w0 + 2);
-            println!("{:<w$} {:^w1$} {:^w2$} {:^w1$} {:^w2$}", "", "(#)", "(bp)", "(#)", "(bp)", w = w0 + 2);
-            for (step, n50_count, n50_base, total_count, toal_base) in nxs {
-                println!(">={step:<w0$} {n50_count:^w1$} {n50_base:^w2$} {total_count:^w1$} {toal_base:^w2$}");
+            let w1 = max(nxs[0].1.to_string().len(), 9);
+            let w2 = max(nxs[0].2.to_string().len(), 10);
+            print!(
+                "{:<w$} {:^w1$} {:^w2$} {:^w1$} {:^w2$}",
+                "Types",
+                "Count",
+                "Length",
+                "N50 Count",
+                "N50 Length",
+                w = w0 + 2
+            );
+            if genome_len > 0 {
+                print!(" {:^w1$} {:^w2$}", "NG50 Count", "NG50 Length");
+            }
+            println!();
+            print!(
+                "{:<w$} {:^w1$} {:^w2$} {:^w1$} {:^w2$}",
+                "",
+                "(#)",
+                "(bp)",
+                "(#)",
+                "(bp)",
+                w = w0 + 2
+            );
+            if genome_len > 0 {
+                print!(" {:^w1$} {:^w2$}", "(#)", "(bp)");
+            }
+            println!();
+            for (step, total_count, total_base, nx) in nxs {
+                print!(
+                    ">={step:<w0$} {total_count:^w1$} {total_base:^w2$} {:^w1$} {:^w2$}",
+                    nx.count[0], nx.len[0]
+                );
+                if nx.is_ng() {
+                    match nx.ng(0) {
+                        Some((count, len)) => print!(" {count:^w1$} {len:^w2$}"),
+                        None => print!(" {:^w1$} {:^w2$}", "-", "-"),
+                    }
+                }
+                println!();
             }
         }
     }).unwrap();
 }
 
-fn out_stat(lens: &[u32], total: usize, genome_len: usize) {
-    let total_count = lens.len();
+fn out_stat(runs: &[(u64, u64)], total: u64, qual: &QualStat, base: &BaseStat, opt: &StatOpt) {
+    let total_count = lens::count(runs);
+    let (min_len, max_len) = (runs[0].0, runs[runs.len() - 1].0);
+    let xs = opt.nx.as_deref().unwrap_or(&NX_DEFAULT);
     let (hist, nx) = thread::scope(|work| {
         let hist = work.spawn(move |_| {
-            let dev = total_count / UNIT_BIN * 2;
-            His::new(lens[dev], lens[total_count - max(1, dev)], total_count).fill(lens)
+            // linear bins are cut at a percentile to skip outliers, log bins cover all lengths
+            let dev = if opt.hist_log {
+                0
+            } else {
+                total_count / UNIT_BIN * 2
+            };
+            let s = opt.hist_min.unwrap_or_else(|| lens::nth(runs, dev));
+            // the longest length is kept out of the last bin when log bins cover all lengths
+            let e = opt.hist_max.unwrap_or_else(|| {
+                lens::nth(runs, total_count - max(1, dev)) + opt.hist_log as u64
+            });
+            let bounds = if opt.hist_log {
+                His::log(s, e, opt.hist_bins)
+            } else {
+                let round = opt.hist_min.is_none() && opt.hist_max.is_none();
+                His::linear(s, e, opt.hist_bins, round)
+            };
+            let c = if opt.hist_bases { total } else { total_count };
+            His::new(bounds, c, opt.hist_bases).fill(runs)
         });
 
-        let nx = work
-            .spawn(move |_| Nx::new().fill(lens, if genome_len > 0 { genome_len } else { total }));
+        let nx = work.spawn(move |_| Nx::new(xs).fill(runs, total, opt.genome_len as u64));
         (
             hist.join().expect("Failed to generate histogram!"),
             nx.join().expect("Failed to generate Nx stats!"),
@@ -230,55 +417,152 @@ fn out_stat(lens: &[u32], total: usize, genome_len: usize) {
     })
     .unwrap();
 
+    if opt.format != Format::Table {
+        let row = Row::new()
+            .nx("", &nx)
+            .num("min", min_len)
+            .num("max", max_len)
+            .num("mean", total / total_count)
+            .num("total_count", total_count)
+            .num("total_len", total);
+        let row = qual.row(row);
+        let row = if base.is_empty() {
+            row
+        } else {
+            base.count_row(row)
+        };
+        report::out_stat(opt.format, row, &hist);
+        return;
+    }
+
     println!("{hist}");
     println!("\n\n[length stat]\n{nx}");
     let (sw1, sw2) = nx.get_width();
-    println!("{:<5} {:^sw1$} {:^sw2$}", "Min.", "-", lens[0],);
-    println!(
-        "{:<5} {:^sw1$} {:^sw2$}",
-        "Max.",
-        "-",
-        lens[total_count - 1],
-    );
+    println!("{:<5} {:^sw1$} {:^sw2$}", "Min.", "-", min_len,);
+    println!("{:<5} {:^sw1$} {:^sw2$}", "Max.", "-", max_len,);
     println!("{:<5} {:^sw1$} {:^sw2$}", "Ave.", "-", total / total_count,);
+    println!("{:<5} {:^sw1$} {:^sw2$.2}", "auN", "-", nx.aun);
+    if nx.is_ng() {
+        println!("{:<5} {:^sw1$} {:^sw2$.2}", "auNG", "-", nx.aung);
+    }
     println!("{:<5} {:^sw1$} {:^sw2$}", "Total", total_count, total,);
+    if !base.is_empty() {
+        println!("\n\n[base stat]\n{base}");
+    }
+    if !qual.is_empty() {
+        println!("\n[quality stat]\n{qual}");
+    }
+}
+
+// scaffold, contig and gap lengths for the gap-aware stat
+struct ScfStat {
+    lens: Lens,
+    total: u64,
+    gap: GapStat,
+    base: BaseStat,
 }
 
-fn out_stats(
-    lens: &[u32],
-    total: usize,
-    ctg_lens: &[u32],
-    ctg_total: usize,
-    gap_lens: &[u32],
-    gap_total: usize,
-    genome_len: usize,
-) {
-    let acc_min = |lens: &[u32], min| {
-        lens.iter()
-            .filter(|&&x| x >= min)
-            .fold((0, 0), |acc, x| (acc.0 + 1, acc.1 + x))
+impl ScfStat {
+    fn merge(stats: &[ScfStat], n_len: usize, stream: bool) -> Self {
+        let mut merged = ScfStat::from(ReadStat::new(n_len, stream));
+        for stat in stats {
+            merged.lens.append(stat.lens.clone());
+            merged.total += stat.total;
+            merged.gap.merge(stat.gap.clone());
+            merged.base.merge(&stat.base);
+        }
+        merged
+    }
+
+    // (length runs, total) of scaffolds, contigs and gaps
+    fn types(&mut self) -> [(Runs, u64); 3] {
+        [
+            (self.lens.runs(), self.total),
+            (self.gap.ctg_lens.runs(), self.gap.ctg_total),
+            (self.gap.gap_lens.runs(), self.gap.gap_total),
+        ]
+    }
+}
+
+impl From<ReadStat> for ScfStat {
+    fn from(stat: ReadStat) -> Self {
+        Self {
+            lens: stat.lens,
+            total: stat.total,
+            gap: stat.gap,
+            base: stat.base,
+        }
+    }
+}
+
+fn out_stats(stat: &mut ScfStat, opt: &StatOpt) {
+    let xs = opt.nx.as_deref().unwrap_or(&NX_DEFAULT);
+    let genome_len = opt.genome_len as u64;
+    let types = stat.types();
+    let acc_min = |runs: &[(u64, u64)], min| {
+        runs.iter()
+            .filter(|(len, _)| *len >= min)
+            .fold((0, 0), |acc, (len, c)| (acc.0 + c, acc.1 + len * c))
     };
-    let (nx, ctg_nx, gap_nx) = thread::scope(|work| {
-        let nx = work
-            .spawn(move |_| Nx::new().fill(lens, if genome_len > 0 { genome_len } else { total }));
-        let ctg_nx = work.spawn(move |_| {
-            Nx::new().fill(
-                ctg_lens,
-                if genome_len > 0 {
-                    genome_len
-                } else {
-                    ctg_total
-                },
-            )
+    let nxs = types
+        .par_iter()
+        .enumerate()
+        .map(|(i, (runs, total))| {
+            Nx::new(xs).fill(runs, *total, if i < 2 { genome_len } else { 0 })
+        })
+        .collect::<Vec<_>>();
+
+    // (key, label, [scaffold, contig, gap] cells)
+    let cell = |len: &dyn fmt::Display, count: &dyn fmt::Display| {
+        Cell::Pair(Some(len.to_string()), Some(count.to_string()))
+    };
+    let na = || Cell::Pair(None, None);
+    let mut rows = Vec::new();
+    for (i, x) in xs.iter().enumerate() {
+        let cells = [0, 1, 2].map(|t| cell(&nxs[t].len[i], &nxs[t].count[i]));
+        rows.push((format!("n{x}"), format!("N{x}"), cells));
+    }
+    if genome_len > 0 {
+        for (i, x) in xs.iter().enumerate() {
+            let cells = [0, 1, 2].map(|t| match nxs[t].is_ng().then(|| nxs[t].ng(i)).flatten() {
+                Some((count, len)) => cell(&len, &count),
+                None => na(),
+            });
+            rows.push((format!("ng{x}"), format!("NG{x}"), cells));
+        }
+    }
+    let cells = [0, 1, 2].map(|t| types[t].0.last().map_or(cell(&0, &0), |x| cell(&x.0, &1)));
+    rows.push(("longest".to_string(), "Longest".to_string(), cells));
+    let cells = [0, 1, 2].map(|t| cell(&types[t].1, &lens::count(&types[t].0)));
+    rows.push(("total".to_string(), "Total".to_string(), cells));
+    let cells = [0, 1, 2].map(|t| Cell::Value(Some(format!("{:.2}", nxs[t].aun))));
+    rows.push(("aun".to_string(), "auN".to_string(), cells));
+    if genome_len > 0 {
+        let cells = [0, 1, 2].map(|t| {
+            Cell::Value(nxs[t].is_ng().then(|| format!("{:.2}", nxs[t].aung)))
         });
-        let gap_nx = work.spawn(move |_| Nx::new().fill(gap_lens, gap_total));
-        (
-            nx.join().expect("Failed to generate Nx stats!"),
-            ctg_nx.join().expect("Failed to generate ctg_Nx stats!"),
-            gap_nx.join().expect("Failed to generate gap_Nx stats!"),
-        )
-    })
-    .unwrap();
+        rows.push(("aung".to_string(), "auNG".to_string(), cells));
+    }
+    for (key, label, min_len) in [
+        ("ge10kb", ">=10kb", 10000),
+        ("ge100kb", ">=100kb", 100000),
+        ("ge1mb", ">=1mb", 1000000),
+    ] {
+        let cells = [0, 1, 2].map(|t| {
+            let (count, total) = acc_min(&types[t].0, min_len);
+            cell(&total, &count)
+        });
+        rows.push((key.to_string(), label.to_string(), cells));
+    }
+
+    if opt.format != Format::Table {
+        let rows = rows
+            .into_iter()
+            .map(|(key, _label, cells)| (key, cells))
+            .collect::<Vec<_>>();
+        report::out_stats(opt.format, &rows, stat.base.count_row(Row::new()));
+        return;
+    }
 
     println!("{:=<7}{:=^26}{:=^26}{:=^25}", "", "", "", "");
     println!(
@@ -290,70 +574,194 @@ fn out_stats(
         "", "Length (bp)", "Count (#)", "Length (bp)", "Count (#)", "Length (bp)", "Count (#)"
     );
     println!("{:-<7}{:-^26}{:-^26}{:-^25}", "", "", "", "");
-    for i in 0..9 {
+    for (_key, label, cells) in rows {
+        let [scf, ctg, gap] = cells.map(|cell| {
+            let (len, count) = match cell {
+                Cell::Pair(len, count) => (len, count),
+                Cell::Value(v) => (v, None),
+            };
+            (
+                len.unwrap_or_else(|| "-".to_string()),
+                count.unwrap_or_else(|| "-".to_string()),
+            )
+        });
         println!(
-            "N{:<6}{:^16}{:^10}{:^16}{:^10}{:^16}{:^9}",
-            (i + 1) * 10,
-            nx.len[i],
-            nx.count[i],
-            ctg_nx.len[i],
-            ctg_nx.count[i],
-            gap_nx.len[i],
-            gap_nx.count[i],
+            "{:<7}{:^16}{:^10}{:^16}{:^10}{:^16}{:^9}",
+            label, scf.0, scf.1, ctg.0, ctg.1, gap.0, gap.1,
         );
     }
-    println!(
-        "{:<7}{:^16}{:^10}{:^16}{:^10}{:^16}{:^9}",
-        "Longest",
-        lens.last().unwrap_or(&0),
-        1,
-        ctg_lens.last().unwrap_or(&0),
-        1,
-        gap_lens.last().unwrap_or(&0),
-        if gap_lens.is_empty() { 0 } else { 1 },
-    );
-    println!(
-        "{:<7}{:^16}{:^10}{:^16}{:^10}{:^16}{:^9}",
-        "Total",
-        total,
-        lens.len(),
-        ctg_total,
-        ctg_lens.len(),
-        gap_total,
-        gap_lens.len(),
-    );
+    println!("{:=<7}{:=^26}{:=^26}{:=^25}", "", "", "", "");
+    println!("\n[base stat]\n{}", stat.base);
+}
 
-    for min_len in [10000, 100000, 1000000] {
-        let (len_count, len_total) = acc_min(lens, min_len);
-        let (ctg_count, ctg_total) = acc_min(ctg_lens, min_len);
-        let (gap_count, gap_total) = acc_min(gap_lens, min_len);
-        println!(
-            "{:<7}{:^16}{:^10}{:^16}{:^10}{:^16}{:^9}",
-            if min_len == 1000000 {
-                ">=1mb"
-            } else if min_len == 100000 {
-                ">=100kb"
+// summary columns of sorted lengths, used by the per-file rows
+fn sum_row(
+    row: Row,
+    prefix: &str,
+    runs: &[(u64, u64)],
+    total: u64,
+    xs: &[u32],
+    genome_len: u64,
+) -> Row {
+    let count = lens::count(runs);
+    let nx = Nx::new(xs).fill(runs, total, genome_len);
+    row.num(format!("{prefix}total_count"), count)
+        .num(format!("{prefix}total_len"), total)
+        .num(format!("{prefix}min"), runs.first().map_or(0, |x| x.0))
+        .num(format!("{prefix}max"), runs.last().map_or(0, |x| x.0))
+        .num(
+            format!("{prefix}mean"),
+            total.checked_div(count).unwrap_or(0),
+        )
+        .nx(prefix, &nx)
+}
+
+fn out_files(infiles: &[&str], mut stats: Vec<ReadStat>, opt: &StatOpt) {
+    let xs = opt.nx.as_deref().unwrap_or(&NX_FILE);
+    let file_row = |name: &str, stat: &mut ReadStat| {
+        let row = sum_row(
+            Row::new().str("file", name),
+            "",
+            &stat.lens.runs(),
+            stat.total,
+            xs,
+            opt.genome_len as u64,
+        );
+        stat.base.row(stat.qual.row(row))
+    };
+    let mut rows = infiles
+        .iter()
+        .zip(stats.iter_mut())
+        .map(|(infile, stat)| file_row(infile, stat))
+        .collect::<Vec<_>>();
+    if opt.all {
+        rows.push(file_row("all", &mut ReadStat::merge(stats, 0, opt.stream)));
+    }
+    report::out_rows(opt.format, &rows);
+}
+
+fn out_scf_files(infiles: &[&str], stats: &mut [ScfStat], opt: &StatOpt) {
+    let xs = opt.nx.as_deref().unwrap_or(&NX_FILE);
+    let scf_row = |name: &str, stat: &mut ScfStat| {
+        let row = Row::new().str("file", name);
+        let row = ["scaffold_", "contig_", "gap_"]
+            .iter()
+            .zip(stat.types())
+            .enumerate()
+            .fold(row, |row, (i, (prefix, (runs, total)))| {
+                sum_row(
+                    row,
+                    prefix,
+                    &runs,
+                    total,
+                    xs,
+                    if i < 2 { opt.genome_len as u64 } else { 0 },
+                )
+            });
+        stat.base.row(row)
+    };
+    let mut rows = infiles
+        .iter()
+        .zip(stats.iter_mut())
+        .map(|(infile, stat)| scf_row(infile, stat))
+        .collect::<Vec<_>>();
+    if opt.all {
+        rows.push(scf_row(
+            "all",
+            &mut ScfStat::merge(stats, opt.n_len, opt.stream),
+        ));
+    }
+    report::out_rows(opt.format, &rows);
+}
+
+// name of a file (None if combined), [(field prefix, length runs, total)]
+type CurveUnit<'a> = (Option<&'a str>, Vec<(&'a str, Runs, u64)>);
+
+// the Nx curve at 1% steps
+fn out_curve(units: &[CurveUnit], opt: &StatOpt) {
+    let xs = (1..=100).collect::<Vec<_>>();
+    let mut rows = Vec::new();
+    for (name, types) in units {
+        let nxs = types
+            .par_iter()
+            .map(|(_prefix, runs, total)| Nx::new(&xs).fill(runs, *total, opt.genome_len as u64))
+            .collect::<Vec<_>>();
+        for (i, x) in xs.iter().enumerate() {
+            let row = name.map_or_else(Row::new, |name| Row::new().str("file", name));
+            let row = types
+                .iter()
+                .zip(&nxs)
+                .fold(row.num("x", x), |row, ((prefix, _, _), nx)| {
+                    let row = row
+                        .num(format!("{prefix}count"), nx.count[i])
+                        .num(format!("{prefix}len"), nx.len[i]);
+                    if nx.is_ng() {
+                        let ng = nx.ng(i);
+                        row.opt(format!("{prefix}ng_count"), ng.map(|x| x.0))
+                            .opt(format!("{prefix}ng_len"), ng.map(|x| x.1))
+                    } else {
+                        row
+                    }
+                });
+            rows.push(row);
+        }
+    }
+    report::out_rows(opt.format, &rows);
+}
+
+// lengths, qualities and bases of reads from the scanner
+struct ReadStat {
+    lens: Lens,
+    total: u64,
+    qual: QualStat,
+    base: BaseStat,
+    gap: GapStat,
+}
+
+impl ReadStat {
+    fn new(n_len: usize, stream: bool) -> Self {
+        Self {
+            lens: if stream {
+                Lens::new(true)
             } else {
-                ">=10kb"
+                Lens::All(Vec::with_capacity(1024000))
             },
-            len_total,
-            len_count,
-            ctg_total,
-            ctg_count,
-            gap_total,
-            gap_count
-        );
+            total: 0,
+            qual: QualStat::new(),
+            base: BaseStat::new(),
+            gap: GapStat::new(n_len, stream),
+        }
+    }
+
+    fn add(&mut self, stat: ReadStat) {
+        self.lens.append(stat.lens);
+        self.total += stat.total;
+        self.qual.merge(&stat.qual);
+        self.base.merge(&stat.base);
+        self.gap.merge(stat.gap);
+    }
+
+    fn merge(stats: Vec<ReadStat>, n_len: usize, stream: bool) -> Self {
+        let mut merged = ReadStat::new(n_len, stream);
+        for stat in stats {
+            merged.add(stat);
+        }
+        merged
     }
-    println!("{:=<7}{:=^26}{:=^26}{:=^25}", "", "", "", "");
 }
 
+// return the stat of each input file if per_file, otherwise of all input files,
+// qualities and bases are counted only if detail, contigs and gaps only if n_len > 0,
+// lengths are counted in constant memory if stream, up to threads readers are scanned at the same time
 fn stat_read(
     infiles: &[&str],
     min_len: usize,
-    genome_len: usize,
-    step_len: usize,
-    out: bool,
-) -> (usize, Vec<u32>) {
+    n_len: usize,
+    per_file: bool,
+    detail: bool,
+    stream: bool,
+    threads: usize,
+) -> Vec<ReadStat> {
     // exit if any thread panics
     let orig_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |v| {
@@ -361,35 +769,184 @@ fn stat_read(
         std::process::exit(1);
     }));
 
-    let (mut lens, total) = thread::scope(|work| {
-        let (s1, r1) = unbounded();
-        let (s2, r2) = unbounded();
-        for _ in 0..BUF_COUNT {
-            s1.send(Buffer::new()).unwrap();
+    // threads are shared by the files of the inputs and fofns first, the rest decompress
+    // BGZF blocks, each file is opened by the worker reading it
+    let (s, r) = unbounded();
+    let mut n = 0;
+    for (i, infile) in infiles.iter().enumerate() {
+        for file in expand_path(infile) {
+            s.send((n, if per_file { i } else { 0 }, file)).unwrap();
+            n += 1;
         }
+    }
+    drop(s);
+    let gz_threads = max(1, threads / max(n, 1));
 
-        // read thread
-        work.spawn(move |_| {
-            for infile in infiles {
-                for mut reader in open_path(infile) {
-                    loop {
-                        let mut buf = r1.recv().unwrap();
-                        match buf.fill(&mut reader) {
-                            Ok(0) => {
-                                let is_empty = buf.is_empty();
-                                s2.send(Some(buf)).unwrap(); //send an empty buf to indicate this file reaches EOF
-                                if is_empty {
-                                    break;
-                                }
-                            }
-                            Ok(_n) => s2.send(Some(buf)).unwrap(),
-                            Err(e) => panic!("Failed to read file: {infile:?}, error: {e:?}"),
+    let mut stats = thread::scope(|work| {
+        let handles = (0..min(threads, r.len()).max(1))
+            .map(|_| {
+                let r = r.clone();
+                work.spawn(move |_| {
+                    let mut stats = Vec::new();
+                    while let Ok((n, i, file)) = r.recv() {
+                        // stdin may still be a fofn of several files
+                        for reader in open_path(&file, gz_threads) {
+                            stats.push((
+                                n,
+                                i,
+                                stat_reader(&file, reader, min_len, n_len, detail, stream),
+                            ));
                         }
                     }
+                    stats
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    })
+    .unwrap();
+
+    // merge in the input order, the same as reading the files one by one
+    stats.sort_by_key(|x| x.0);
+    let mut merged = Vec::new();
+    merged.resize_with(if per_file { infiles.len() } else { 1 }, || {
+        ReadStat::new(n_len, stream)
+    });
+    for (_n, i, stat) in stats {
+        merged[i].add(stat);
+    }
+    merged
+}
+
+// the same as stat_read, but only lengths are read from the <INPUT>.fai of each input file
+fn stat_fai(infiles: &[&str], min_len: usize, per_file: bool, stream: bool) -> Vec<ReadStat> {
+    let mut stats = Vec::new();
+    stats.resize_with(if per_file { infiles.len() } else { 1 }, || {
+        ReadStat::new(0, stream)
+    });
+    for (i, infile) in infiles.iter().enumerate() {
+        let stat = &mut stats[if per_file { i } else { 0 }];
+        let fai = format!("{infile}.fai");
+        let file = File::open(&fai).unwrap_or_else(|_| panic!("Failed open file {fai:?}"));
+        for line in BufReader::new(file).lines().map_while(Result::ok) {
+            let len = line
+                .split('\t')
+                .nth(1)
+                .and_then(|x| x.parse::<u64>().ok())
+                .unwrap_or_else(|| panic!("invalid line in {fai}: {line}"));
+            if len > min_len as u64 {
+                stat.lens.push(len);
+                stat.total += len;
+            }
+        }
+    }
+    stats
+}
+
+// the same as stat_read with n_len, but records are parsed to write contigs to <INPUT>.ctg.fa,
+// and their placement in scaffolds to <INPUT>.ctg.agp
+fn stat_ctg(infiles: &[&str], opt: &StatOpt) -> Vec<ReadStat> {
+    let mut stats = Vec::new();
+    stats.resize_with(if opt.per_file { infiles.len() } else { 1 }, || {
+        ReadStat::new(opt.n_len, opt.stream)
+    });
+    for (i, infile) in infiles.iter().enumerate() {
+        let stat = &mut stats[if opt.per_file { i } else { 0 }];
+        let mut records = parse_fx(infile);
+        let out = infile.to_string() + ".ctg.fa";
+        let mut out = BufWriter::new(
+            File::create(&out).unwrap_or_else(|_| panic!("failed create file: {out}")),
+        );
+        let agp = infile.to_string() + ".ctg.agp";
+        let mut agp = BufWriter::new(
+            File::create(&agp).unwrap_or_else(|_| panic!("failed create file: {agp}")),
+        );
+        writeln!(agp, "##agp-version\t2.1").unwrap();
+        while let Some(record) = records.iter_record().unwrap() {
+            let len = record.len();
+            if len == 0 || len < opt.min_len {
+                continue;
+            }
+            let seq = record.seq();
+            stat.lens.push(len as u64);
+            stat.total += len as u64;
+            stat.base.add(seq.as_bytes());
+            stat.base.end_record(true);
+            stat.gap.add(seq.as_bytes());
+
+            let (mut pos, mut ctg_count) = (0, 1);
+            let parts = stat.gap.parts();
+            if parts.first().is_some_and(|x| x.0) || parts.last().is_some_and(|x| x.0) {
+                eprintln!(
+                    "Warning: {} starts or ends with a gap, which is not allowed in AGP",
+                    record.head()
+                );
+            }
+            for (part, (is_gap, l)) in parts.iter().enumerate() {
+                let l = *l as usize;
+                let head = format!("{}\t{}\t{}\t{}", record.head(), pos + 1, pos + l, part + 1);
+                if *is_gap {
+                    writeln!(agp, "{head}\tN\t{l}\tscaffold\tyes\tunspecified").unwrap();
+                } else {
+                    writeln!(
+                        agp,
+                        "{head}\tW\t{}_ctg{}\t1\t{l}\t+",
+                        record.head(),
+                        ctg_count
+                    )
+                    .unwrap();
+                    writeln!(
+                        out,
+                        ">{}_ctg{}\n{}",
+                        record.head(),
+                        ctg_count,
+                        &seq[pos..pos + l]
+                    )
+                    .unwrap();
+                    ctg_count += 1;
+                }
+                pos += l;
+            }
+            stat.gap.end_record(true);
+        }
+    }
+    stats
+}
+
+// stat of the records from a single reader
+fn stat_reader(
+    infile: &str,
+    mut reader: Box<dyn Read + Send>,
+    min_len: usize,
+    n_len: usize,
+    detail: bool,
+    stream: bool,
+) -> ReadStat {
+    let (s1, r1) = unbounded();
+    let (s2, r2) = unbounded();
+    for _ in 0..BUF_COUNT {
+        s1.send(Buffer::new()).unwrap();
+    }
+
+    thread::scope(|work| {
+        // read thread, r1 is borrowed to keep the returned buffers receivable until the stat thread finishes
+        let r1 = &r1;
+        work.spawn(move |_| loop {
+            let mut buf = r1.recv().unwrap();
+            match buf.fill(&mut reader) {
+                Ok(0) => {
+                    let is_empty = buf.is_empty();
+                    s2.send(buf).unwrap(); //send an empty buf to indicate the reader reaches EOF
+                    if is_empty {
+                        break;
+                    }
                 }
+                Ok(_n) => s2.send(buf).unwrap(),
+                Err(e) => panic!("Failed to read file: {infile:?}, error: {e:?}"),
             }
-            s2.send(None).unwrap();
-            while r1.len() != BUF_COUNT {} //wait the stat thread to finish
         });
 
         // statistics thread
@@ -398,23 +955,31 @@ fn stat_read(
             let mut skip_bases = 0;
             let mut is_new_record = true;
 
+            let mut qual_len = 0; // length of the read whose quality is being counted
+            let is_qual = |len| if detail && len > min_len { len } else { 0 };
+
             let mut len = 0;
-            let mut lens = Vec::with_capacity(1024000);
-            let mut total: usize = 0;
-            while let Ok(Some(mut buf)) = r2.recv() {
+            let mut stat = ReadStat::new(n_len, stream);
+            let ReadStat {
+                lens,
+                total,
+                qual: qual_stat,
+                base: base_stat,
+                gap: gap_stat,
+            } = &mut stat;
+            while let Ok(mut buf) = r2.recv() {
                 if buf.is_empty() {
-                    // a file has reached EOF
+                    // the reader has reached EOF
                     if skip_bases != 0 || skip_lines != 0 {
                         panic!("truncate file");
                     } else if len > min_len {
                         // save the last fasta record
-                        lens.push(len as u32);
-                        total += len;
+                        lens.push(len as u64);
+                        *total += len as u64;
                     }
-                    len = 0;
-                    is_new_record = true;
-                    s1.send(buf).unwrap();
-                    continue;
+                    base_stat.end_record(len > min_len);
+                    gap_stat.end_record(len > min_len);
+                    break;
                 }
 
                 if skip_lines > 0 {
@@ -427,7 +992,7 @@ fn stat_read(
                 }
 
                 if skip_bases > 0 {
-                    let skip_base = buf.skip_bases(skip_bases);
+                    let skip_base = qual_stat.skip(&mut buf, skip_bases, qual_len);
                     skip_bases -= skip_base;
                     if skip_bases > 0 {
                         s1.send(buf).unwrap();
@@ -440,18 +1005,23 @@ fn stat_read(
                         if c == b'>' || c == b'@' {
                             if len > min_len {
                                 // save the previous fasta record
-                                lens.push(len as u32);
-                                total += len;
+                                lens.push(len as u64);
+                                *total += len as u64;
                             }
+                            base_stat.end_record(len > min_len);
+                            gap_stat.end_record(len > min_len);
                             len = 0;
                             is_new_record = true;
                             continue;
                         } else if c == b'+' {
                             if len > min_len {
                                 // save the previous fasta record
-                                lens.push(len as u32);
-                                total += len;
+                                lens.push(len as u64);
+                                *total += len as u64;
                             }
+                            base_stat.end_record(len > min_len);
+                            gap_stat.end_record(len > min_len);
+                            qual_len = is_qual(len);
                             let skip_line = buf.skip_lines(1); //skip sep
                             if skip_line != 1 {
                                 skip_lines = 1;
@@ -459,7 +1029,7 @@ fn stat_read(
                                 break;
                             }
 
-                            let l = buf.skip_bases(len); // skip qual
+                            let l = qual_stat.skip(&mut buf, len, qual_len); // skip qual
                             is_new_record = true;
                             if l != len {
                                 skip_bases = len - l;
@@ -481,15 +1051,22 @@ fn stat_read(
                         panic!("Not a correct fasta/fastq file");
                     }
 
-                    while let Some((p, _a)) = buf.next_line_len() {
+                    while let Some((line, _a)) = buf.next_line() {
                         // iter seq
-                        len += p;
+                        len += line.len();
+                        if detail {
+                            base_stat.add(line);
+                        }
+                        if n_len > 0 {
+                            gap_stat.add(line);
+                        }
                         if let Some(c) = buf.next_byte(false) {
                             if c == b'>' {
                                 // fasta
                                 break;
                             } else if c == b'+' {
                                 // fastq
+                                qual_len = is_qual(len);
                                 let skip_line = buf.skip_lines(1); //skip sep
                                 if skip_line != 1 {
                                     skip_lines = 1;
@@ -497,7 +1074,7 @@ fn stat_read(
                                     break;
                                 }
 
-                                let l = buf.skip_bases(len); // skip qual
+                                let l = qual_stat.skip(&mut buf, len, qual_len); // skip qual
                                 is_new_record = true;
                                 if l != len {
                                     skip_bases = len - l;
@@ -511,115 +1088,173 @@ fn stat_read(
                     }
                     if len > 0 {
                         if len > min_len {
-                            lens.push(len as u32);
-                            total += len;
+                            lens.push(len as u64);
+                            *total += len as u64;
                         }
+                        base_stat.end_record(len > min_len);
+                        gap_stat.end_record(len > min_len);
                         len = 0;
                         is_new_record = true;
                     }
                 }
                 s1.send(buf).unwrap();
             }
-            (lens, total)
+            stat
         });
         stat.join().expect("Failed to read from input file!")
     })
-    .unwrap();
+    .unwrap()
+}
+
+pub struct StatOpt {
+    pub min_len: usize,
+    pub genome_len: usize,
+    pub n_len: usize,
+    pub step_len: usize,
+    pub out_ctg: bool,
+    pub per_file: bool,
+    pub all: bool,
+    pub format: Format,
+    pub nx: Option<Vec<u32>>,
+    pub nx_curve: bool,
+    pub threads: usize,
+    pub stream: bool,
+    pub fai: bool,
+    pub hist_bins: u64,
+    pub hist_log: bool,
+    pub hist_min: Option<u64>,
+    pub hist_max: Option<u64>,
+    pub hist_bases: bool,
+}
 
-    if out && !lens.is_empty() {
-        lens.par_sort_unstable();
-        if step_len > 0 {
-            out_step(&lens, step_len as u32, genome_len);
+pub fn stat(infiles: &[&str], opt: &StatOpt) {
+    if opt.n_len == 0 {
+        let mut stats = if opt.fai {
+            stat_fai(infiles, opt.min_len, opt.per_file, opt.stream)
         } else {
-            out_stat(&lens, total, genome_len);
+            stat_read(
+                infiles,
+                opt.min_len,
+                0,
+                opt.per_file,
+                true,
+                opt.stream,
+                opt.threads,
+            )
+        };
+        if opt.nx_curve {
+            let units = stats
+                .iter_mut()
+                .enumerate()
+                .map(|(i, stat)| {
+                    (
+                        opt.per_file.then(|| infiles[i]),
+                        vec![("", stat.lens.runs(), stat.total)],
+                    )
+                })
+                .collect::<Vec<_>>();
+            out_curve(&units, opt);
+            return;
+        }
+        if opt.per_file {
+            out_files(infiles, stats, opt);
+            return;
         }
+        let ReadStat {
+            lens,
+            total,
+            qual,
+            base,
+            ..
+        } = &mut stats[0];
+        let runs = lens.runs();
+        if !runs.is_empty() {
+            if opt.step_len > 0 {
+                out_step(&runs, opt);
+            } else {
+                out_stat(&runs, *total, qual, base, opt);
+            }
+        }
+        return;
+    }
+
+    // the gap stat keeps sequences as long as min_len
+    let stats = if opt.out_ctg {
+        stat_ctg(infiles, opt)
+    } else {
+        stat_read(
+            infiles,
+            opt.min_len.saturating_sub(1),
+            opt.n_len,
+            opt.per_file,
+            true,
+            opt.stream,
+            opt.threads,
+        )
     };
-    (total, lens)
+    let mut stats = stats.into_iter().map(ScfStat::from).collect::<Vec<_>>();
+    if opt.nx_curve {
+        let units = stats
+            .iter_mut()
+            .enumerate()
+            .map(|(i, stat)| {
+                (
+                    opt.per_file.then(|| infiles[i]),
+                    vec![
+                        ("scaffold_", stat.lens.runs(), stat.total),
+                        ("contig_", stat.gap.ctg_lens.runs(), stat.gap.ctg_total),
+                    ],
+                )
+            })
+            .collect::<Vec<_>>();
+        out_curve(&units, opt);
+    } else if opt.per_file {
+        out_scf_files(infiles, &mut stats, opt);
+    } else {
+        out_stats(&mut stats[0], opt);
+    }
 }
 
-pub fn stat(
-    infiles: &[&str],
-    min_len: usize,
-    genome_len: usize,
-    n_len: usize,
-    step_len: usize,
-    out_ctg: bool,
-) {
-    if n_len == 0 {
-        stat_read(infiles, min_len, genome_len, step_len, true);
-        return;
-    }
-    let mut lens = Vec::with_capacity(1024);
-    let mut total: usize = 0;
+pub fn sum_fx(infiles: &[&str]) -> usize {
+    stat_read(infiles, 0, 0, false, false, true, 1)[0].total as usize
+}
 
-    let re = Regex::new(&format!("(?i)N{{{n_len},}}")).unwrap();
-    let mut ctg_lens = Vec::with_capacity(1024);
-    let mut ctg_total: usize = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, io::Cursor};
 
-    let mut gap_lens = Vec::with_capacity(1024);
-    let mut gap_total: usize = 0;
+    #[test]
+    fn nx_ties() {
+        // the N50 sequence is the one the cumulative length exceeds half the total at
+        let nx = Nx::new(&[10, 50, 75, 100]).fill(&[(10, 4)], 40, 0);
+        assert_eq!(nx.count, [1, 3, 4, 4]);
+        assert_eq!(nx.len, [10, 10, 10, 10]);
+        let nx = Nx::new(&[50, 100]).fill(&[(5, 2), (10, 1)], 20, 40);
+        assert_eq!((nx.count, nx.g_count), (vec![2, 3], vec![0, 0]));
+    }
 
-    for infile in infiles {
-        let mut records = parse_fx(infile);
-        let out = out_ctg.then(|| {
-            let out = infile.to_string() + ".ctg.fa";
-            File::create(&out).unwrap_or_else(|_| panic!("failed create file: {out}"))
-        });
-        while let Some(record) = records.iter_record().unwrap() {
-            let len = record.len();
-            if len < min_len {
-                continue;
-            }
-            lens.push(len as u32);
-            total += len;
+    #[test]
+    fn linear_bins_end_at_max() {
+        assert_eq!(His::linear(0, 3000, 5, false), [0, 1000, 2000, 3000]);
+        assert_eq!(His::linear(1, 3000, 5, false), [1, 1000, 2000, 3000]);
+        assert_eq!(His::linear(0, 2, 5, false), [0, 1, 2]);
+    }
 
-            let mut last_pos = 0;
-            let mut ctg_count = 1;
-            let seq = record.seq();
-            for mat in re.find_iter(seq) {
-                if mat.start() > last_pos {
-                    ctg_lens.push((mat.start() - last_pos) as u32);
-                    ctg_total += mat.start() - last_pos;
-                    if out_ctg {
-                        writeln!(
-                            out.as_ref().unwrap(),
-                            ">{}_ctg{}\n{}",
-                            record.head(),
-                            ctg_count,
-                            &seq[last_pos..mat.start()]
-                        )
-                        .unwrap();
-                        ctg_count += 1;
-                    }
-                }
-                gap_lens.push((mat.end() - mat.start()) as u32);
-                gap_total += mat.end() - mat.start();
-                last_pos = mat.end();
-            }
-            if len > last_pos {
-                ctg_lens.push((len - last_pos) as u32);
-                ctg_total += len - last_pos;
-                if out_ctg {
-                    writeln!(
-                        out.as_ref().unwrap(),
-                        ">{}_ctg{}\n{}",
-                        record.head(),
-                        ctg_count,
-                        &seq[last_pos..len]
-                    )
-                    .unwrap();
-                }
-            }
+    #[test]
+    fn fai_min_len_as_scan() {
+        let fa = ">a\nACGTACGT\n>b\nACGTACGTA\n";
+        let path = std::env::temp_dir().join(format!("fxTools.stat.{}.fa", std::process::id()));
+        let path = path.to_str().unwrap();
+        let fai = format!("{path}.fai");
+        fs::write(&fai, "a\t8\t3\t8\t9\nb\t9\t15\t9\t10\n").unwrap();
+        for min_len in [0, 7, 8, 9] {
+            let mut fai_stat = stat_fai(&[path], min_len, false, false).remove(0);
+            let reader = Box::new(Cursor::new(fa.as_bytes().to_vec()));
+            let mut scan_stat = stat_reader(path, reader, min_len, 0, false, false);
+            assert_eq!(fai_stat.total, scan_stat.total, "min_len {min_len}");
+            assert_eq!(fai_stat.lens.runs(), scan_stat.lens.runs(), "min_len {min_len}");
         }
-        lens.par_sort_unstable();
-        ctg_lens.par_sort_unstable();
-        gap_lens.par_sort_unstable();
-        out_stats(
-            &lens, total, &ctg_lens, ctg_total, &gap_lens, gap_total, genome_len,
-        );
+        fs::remove_file(fai).unwrap();
     }
 }
-
-pub fn sum_fx(infiles: &[&str]) -> usize {
-    stat_read(infiles, 0, 0, 0, false).0
-}
@@ -0,0 +1,140 @@
+use super::{io::Buffer, report::Row};
+use crate::utils::common::{PHRED_OFFSET, QS_ARRAY};
+use std::{cmp::max, fmt};
+
+const READ_Q_BIN: f64 = 10.0; // bins per quality unit of the read quality histogram
+
+#[derive(Default)]
+pub struct QualStat {
+    bases: Vec<usize>,  // base count of each quality char
+    read_q: Vec<usize>, // base count of reads binned by read quality
+    q_sum: f64,
+    reads: usize,
+    err_sum: f64, // error sum of the read in progress
+}
+
+impl QualStat {
+    pub fn new() -> Self {
+        Self {
+            bases: vec![0; 256],
+            ..Default::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reads == 0
+    }
+
+    // skip at most len quality values from buf, read_len is the length of the read
+    // they belong to, or 0 if the read is not counted
+    pub fn skip(&mut self, buf: &mut Buffer, len: usize, read_len: usize) -> usize {
+        if read_len == 0 {
+            return buf.skip_bases(len);
+        }
+        let l = buf.skip_bases_with(len, |qual| {
+            for q in qual {
+                self.bases[*q as usize] += 1;
+                // chars below the phred offset are taken as Q0
+                self.err_sum += QS_ARRAY[max(*q as usize, PHRED_OFFSET)];
+            }
+        });
+        if l == len {
+            // the read Q of attr: -10 * log10(mean error)
+            let q = -10.0 * (self.err_sum / read_len as f64).log10();
+            let bin = (q * READ_Q_BIN).round() as usize;
+            if bin >= self.read_q.len() {
+                self.read_q.resize(bin + 1, 0);
+            }
+            self.read_q[bin] += read_len;
+            self.q_sum += q;
+            self.reads += 1;
+            self.err_sum = 0.0;
+        }
+        l
+    }
+
+    pub fn merge(&mut self, other: &QualStat) {
+        if self.bases.is_empty() {
+            self.bases.resize(256, 0);
+        }
+        for (a, b) in self.bases.iter_mut().zip(&other.bases) {
+            *a += b;
+        }
+        if self.read_q.len() < other.read_q.len() {
+            self.read_q.resize(other.read_q.len(), 0);
+        }
+        for (a, b) in self.read_q.iter_mut().zip(&other.read_q) {
+            *a += b;
+        }
+        self.q_sum += other.q_sum;
+        self.reads += other.reads;
+    }
+
+    // percentage of bases with quality >= q
+    fn q_pct(&self, q: usize) -> f64 {
+        let total = self.bases.iter().sum::<usize>();
+        let pass = self.bases.iter().skip(q + PHRED_OFFSET).sum::<usize>();
+        pass as f64 * 100.0 / total as f64
+    }
+
+    fn mean_read_q(&self) -> f64 {
+        self.q_sum / self.reads as f64
+    }
+
+    // the read quality at which reads with higher or equal quality contain half of the bases
+    fn read_q_n50(&self) -> f64 {
+        let total = self.read_q.iter().sum::<usize>();
+        let mut acc = 0;
+        for (bin, bases) in self.read_q.iter().enumerate().rev() {
+            acc += bases;
+            if acc * 2 >= total {
+                return bin as f64 / READ_Q_BIN;
+            }
+        }
+        0.0
+    }
+
+    pub fn row(&self, row: Row) -> Row {
+        let v = |f: fn(&Self) -> f64| (!self.is_empty()).then(|| format!("{:.2}", f(self)));
+        row.opt("q20_pct", v(|x| x.q_pct(20)))
+            .opt("q30_pct", v(|x| x.q_pct(30)))
+            .opt("mean_read_qual", v(Self::mean_read_q))
+            .opt("read_qual_n50", v(Self::read_q_n50))
+    }
+}
+
+impl fmt::Display for QualStat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<12} {:^9}", "Types", "Value")?;
+        writeln!(f, "{:<12} {:^9.2}", "Q20 (%)", self.q_pct(20))?;
+        writeln!(f, "{:<12} {:^9.2}", "Q30 (%)", self.q_pct(30))?;
+        writeln!(f, "{:<12} {:^9.2}", "Mean read Q", self.mean_read_q())?;
+        writeln!(f, "{:<12} {:^9.1}", "Read Q N50", self.read_q_n50())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stat_of(qual: &[u8]) -> QualStat {
+        let mut buf = Buffer::new();
+        buf.fill(&mut &[qual, b"\n"].concat()[..]).unwrap();
+        let mut stat = QualStat::new();
+        assert_eq!(stat.skip(&mut buf, qual.len(), qual.len()), qual.len());
+        stat
+    }
+
+    #[test]
+    fn read_q_bins_are_rounded() {
+        let stat = stat_of(b"####");
+        assert_eq!(stat.read_q_n50(), 2.0);
+    }
+
+    #[test]
+    fn chars_below_offset_are_q0() {
+        let stat = stat_of(b"\x20\x1f!!");
+        assert_eq!(stat.read_q_n50(), 0.0);
+        assert_eq!(stat.read_q.len(), 1);
+    }
+}
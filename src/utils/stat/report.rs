@@ -0,0 +1,290 @@
+use super::{His, Nx};
+use std::{cmp::max, fmt::Display, str::FromStr};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Table,
+    Tsv,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "table" => Ok(Format::Table),
+            "tsv" => Ok(Format::Tsv),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown output format: {s}")),
+        }
+    }
+}
+
+pub fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// a list of named fields, rendered as a tsv line or a json object
+#[derive(Default)]
+pub struct Row {
+    fields: Vec<(String, String, String)>, // key, tsv value, json value
+}
+
+impl Row {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn num<T: Display>(mut self, key: impl Into<String>, v: T) -> Self {
+        let v = v.to_string();
+        self.fields.push((key.into(), v.clone(), v));
+        self
+    }
+
+    // a number, or NA (null in json) if missing
+    pub fn opt<T: Display>(mut self, key: impl Into<String>, v: Option<T>) -> Self {
+        match v {
+            Some(v) => self.num(key, v),
+            None => {
+                self.fields
+                    .push((key.into(), "NA".to_string(), "null".to_string()));
+                self
+            }
+        }
+    }
+
+    pub fn str(mut self, key: impl Into<String>, v: &str) -> Self {
+        self.fields.push((key.into(), v.to_string(), json_str(v)));
+        self
+    }
+
+    // Nx (and NGx if a genome size is given) at each threshold, then auN
+    pub(super) fn nx(self, prefix: &str, nx: &Nx) -> Self {
+        let row = nx.xs.iter().enumerate().fold(self, |row, (i, x)| {
+            row.num(format!("{prefix}n{x}_count"), nx.count[i])
+                .num(format!("{prefix}n{x}_len"), nx.len[i])
+        });
+        let row = if nx.is_ng() {
+            nx.xs.iter().enumerate().fold(row, |row, (i, x)| {
+                let ng = nx.ng(i);
+                row.opt(format!("{prefix}ng{x}_count"), ng.map(|x| x.0))
+                    .opt(format!("{prefix}ng{x}_len"), ng.map(|x| x.1))
+            })
+        } else {
+            row
+        };
+        let row = row.num(format!("{prefix}aun"), format!("{:.2}", nx.aun));
+        if nx.is_ng() {
+            row.num(format!("{prefix}aung"), format!("{:.2}", nx.aung))
+        } else {
+            row
+        }
+    }
+
+    pub fn tsv_head(&self) -> String {
+        self.fields
+            .iter()
+            .map(|x| x.0.as_str())
+            .collect::<Vec<_>>()
+            .join("\t")
+    }
+
+    pub fn tsv(&self) -> String {
+        self.fields
+            .iter()
+            .map(|x| x.1.as_str())
+            .collect::<Vec<_>>()
+            .join("\t")
+    }
+
+    pub fn json(&self) -> String {
+        self.json_with(&[])
+    }
+
+    // json object with extra members whose values are already json encoded
+    pub fn json_with(&self, extra: &[(&str, String)]) -> String {
+        let members = self
+            .fields
+            .iter()
+            .map(|(k, _, v)| format!("{}:{v}", json_str(k)))
+            .chain(extra.iter().map(|(k, v)| format!("{}:{v}", json_str(k))))
+            .collect::<Vec<_>>();
+        format!("{{{}}}", members.join(","))
+    }
+}
+
+pub fn json_array(rows: &[Row]) -> String {
+    format!(
+        "[{}]",
+        rows.iter().map(|x| x.json()).collect::<Vec<_>>().join(",")
+    )
+}
+
+// print rows sharing the same fields as a tsv table
+pub fn print_tsv(rows: &[Row]) {
+    if let Some(row) = rows.first() {
+        println!("{}", row.tsv_head());
+    }
+    for row in rows {
+        println!("{}", row.tsv());
+    }
+}
+
+// print rows sharing the same fields as an aligned table
+pub fn print_table(rows: &[Row]) {
+    let Some(head) = rows.first() else {
+        return;
+    };
+    let mut ws = head.fields.iter().map(|x| x.0.len()).collect::<Vec<_>>();
+    for row in rows {
+        for (w, field) in ws.iter_mut().zip(&row.fields) {
+            *w = max(*w, field.1.len());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(&ws)
+            .enumerate()
+            .map(|(i, (v, w))| {
+                if i == 0 {
+                    format!("{v:<w$}")
+                } else {
+                    format!("{v:>w$}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+    println!(
+        "{}",
+        line(head.fields.iter().map(|x| x.0.as_str()).collect())
+    );
+    for row in rows {
+        println!(
+            "{}",
+            line(row.fields.iter().map(|x| x.1.as_str()).collect())
+        );
+    }
+}
+
+pub fn out_rows(format: Format, rows: &[Row]) {
+    match format {
+        Format::Table => print_table(rows),
+        Format::Tsv => print_tsv(rows),
+        Format::Json => println!("{}", json_array(rows)),
+    }
+}
+
+fn hist_rows(hist: &His) -> Vec<Row> {
+    hist.bins()
+        .into_iter()
+        .map(|(s, e, c)| {
+            Row::new()
+                .num("bin_start", s)
+                .num("bin_end", e)
+                .num(if hist.weighted { "bases" } else { "count" }, c)
+        })
+        .collect()
+}
+
+pub fn out_stat(format: Format, stat: Row, hist: &His) {
+    let hist = hist_rows(hist);
+    match format {
+        Format::Tsv => {
+            print_tsv(&[stat]);
+            println!();
+            print_tsv(&hist);
+        }
+        Format::Json => println!("{}", stat.json_with(&[("histogram", json_array(&hist))])),
+        Format::Table => unreachable!(),
+    }
+}
+
+pub fn out_step(format: Format, steps: &[(u64, u64, u64, Nx)]) {
+    let rows = steps
+        .iter()
+        .map(|(step, count, len, nx)| {
+            Row::new()
+                .num("min_len", step)
+                .num("count", count)
+                .num("len", len)
+                .nx("", nx)
+        })
+        .collect::<Vec<_>>();
+    match format {
+        Format::Tsv => print_tsv(&rows),
+        Format::Json => println!("{}", Row::new().json_with(&[("steps", json_array(&rows))])),
+        Format::Table => unreachable!(),
+    }
+}
+
+// a cell of the stats rows: a length and a count, or a single value such as auN,
+// None for not available
+pub enum Cell {
+    Pair(Option<String>, Option<String>),
+    Value(Option<String>),
+}
+
+// rows: (key, [scaffold, contig, gap] cells)
+pub type StatsRow = (String, [Cell; 3]);
+
+pub fn out_stats(format: Format, rows: &[StatsRow], base: Row) {
+    const TYPES: [&str; 3] = ["scaffold", "contig", "gap"];
+    match format {
+        Format::Tsv => {
+            let rows = rows
+                .iter()
+                .map(|(key, v)| {
+                    TYPES.iter().zip(v).fold(
+                        Row::new().str("type", key),
+                        |row, (t, cell)| {
+                            let (len, count) = match cell {
+                                Cell::Pair(len, count) => (len.as_ref(), count.as_ref()),
+                                Cell::Value(v) => (v.as_ref(), None),
+                            };
+                            row.opt(format!("{t}_len"), len)
+                                .opt(format!("{t}_count"), count)
+                        },
+                    )
+                })
+                .collect::<Vec<_>>();
+            print_tsv(&rows);
+            println!();
+            print_tsv(&[base]);
+        }
+        Format::Json => {
+            let members = TYPES
+                .iter()
+                .enumerate()
+                .map(|(i, t)| {
+                    let row = rows.iter().fold(Row::new(), |row, (key, v)| match &v[i] {
+                        Cell::Pair(len, count) => row
+                            .opt(format!("{key}_len"), len.as_ref())
+                            .opt(format!("{key}_count"), count.as_ref()),
+                        Cell::Value(v) => row.opt(key, v.as_ref()),
+                    });
+                    (*t, row.json())
+                })
+                .chain([("base", base.json())])
+                .collect::<Vec<_>>();
+            println!("{}", Row::new().json_with(&members));
+        }
+        Format::Table => unreachable!(),
+    }
+}
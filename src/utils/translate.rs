@@ -0,0 +1,128 @@
+use super::common::reverse_complement_bases;
+
+// NCBI genetic codes, amino acids of the 64 codons in TCAG order
+#[rustfmt::skip]
+const CODES: [(usize, &str); 27] = [
+    (1, "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (2, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG"),
+    (3, "FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (4, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (5, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG"),
+    (6, "FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (9, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG"),
+    (10, "FFLLSSSSYY**CCCWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (11, "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (12, "FFLLSSSSYY**CC*WLLLSPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (13, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSGGVVVVAAAADDEEGGGG"),
+    (14, "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG"),
+    (15, "FFLLSSSSYY*QCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (16, "FFLLSSSSYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (21, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNNKSSSSVVVVAAAADDEEGGGG"),
+    (22, "FFLLSS*SYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (23, "FF*LSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (24, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG"),
+    (25, "FFLLSSSSYY**CCGWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (26, "FFLLSSSSYY**CC*WLLLAPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (27, "FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (28, "FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (29, "FFLLSSSSYYYYCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (30, "FFLLSSSSYYEECC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (31, "FFLLSSSSYYEECCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (32, "FFLLSSSSYY*WCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (33, "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG"),
+];
+
+pub fn genetic_code(table: usize) -> &'static [u8] {
+    CODES
+        .iter()
+        .find(|x| x.0 == table)
+        .unwrap_or_else(|| panic!("unknown NCBI genetic code table: {table}"))
+        .1
+        .as_bytes()
+}
+
+// index of a base in TCAG order, U as T, None for ambiguous bases
+fn base_index(b: u8) -> Option<usize> {
+    match b {
+        b'T' | b't' | b'U' | b'u' => Some(0),
+        b'C' | b'c' => Some(1),
+        b'A' | b'a' => Some(2),
+        b'G' | b'g' => Some(3),
+        _ => None,
+    }
+}
+
+// translate a sequence from the first base, codons with ambiguous bases are X,
+// stops are * or end the protein if trim_stop, an incomplete last codon is ignored
+pub fn translate(seq: &[u8], code: &[u8], trim_stop: bool) -> String {
+    let mut protein = String::with_capacity(seq.len() / 3);
+    for codon in seq.chunks_exact(3) {
+        let aa = codon
+            .iter()
+            .try_fold(0, |acc, b| Some(acc * 4 + base_index(*b)?))
+            .map_or('X', |i| code[i] as char);
+        if aa == '*' && trim_stop {
+            break;
+        }
+        protein.push(aa);
+    }
+    protein
+}
+
+// frames of a reading frame argument: 1, 2, 3, -1, -2, -3, or 6 for all six
+pub fn parse_frames(frame: &str) -> Vec<i8> {
+    match frame.trim_start_matches('+') {
+        "" => vec![1],
+        "6" => vec![1, 2, 3, -1, -2, -3],
+        x => match x.parse::<i8>() {
+            Ok(x) if (1..=3).contains(&x.abs()) => vec![x],
+            _ => panic!("invalid reading frame: {frame}, must be 1, 2, 3, -1, -2, -3 or 6"),
+        },
+    }
+}
+
+// proteins of the frames of a sequence, with their frame tags such as +1 or -3
+pub fn translate_frames(
+    seq: &str,
+    frames: &[i8],
+    code: &[u8],
+    trim_stop: bool,
+) -> Vec<(String, String)> {
+    let rc = frames
+        .iter()
+        .any(|x| *x < 0)
+        .then(|| reverse_complement_bases(seq));
+    frames
+        .iter()
+        .map(|frame| {
+            let strand = if *frame > 0 {
+                seq
+            } else {
+                rc.as_deref().unwrap()
+            };
+            let start = (frame.unsigned_abs() as usize - 1).min(strand.len());
+            let protein = translate(&strand.as_bytes()[start..], code, trim_stop);
+            (format!("{frame:+}"), protein)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_tables() {
+        let tables = CODES.iter().map(|x| x.0).collect::<Vec<_>>();
+        assert_eq!(
+            tables,
+            [
+                1, 2, 3, 4, 5, 6, 9, 10, 11, 12, 13, 14, 15, 16, 21, 22, 23, 24, 25, 26, 27, 28,
+                29, 30, 31, 32, 33
+            ]
+        );
+        assert!(CODES.iter().all(|x| x.1.len() == 64));
+        // TAG is W in the Balanophoraceae plastid code
+        assert_eq!(translate(b"TAGTAA", genetic_code(32), false), "W*");
+    }
+}
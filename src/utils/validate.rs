@@ -0,0 +1,325 @@
+use super::path::open_file;
+use hashbrown::HashMap;
+use std::io::{self, BufRead, BufReader, Read};
+
+// kinds of problems, in the order of the summary
+const KINDS: [&str; 8] = [
+    "bad_format",
+    "missing_plus",
+    "length_mismatch",
+    "illegal_char",
+    "empty_record",
+    "duplicate_id",
+    "crlf",
+    "truncated",
+];
+const BAD_FORMAT: usize = 0;
+const MISSING_PLUS: usize = 1;
+const LENGTH_MISMATCH: usize = 2;
+const ILLEGAL_CHAR: usize = 3;
+const EMPTY_RECORD: usize = 4;
+const DUPLICATE_ID: usize = 5;
+const CRLF: usize = 6;
+const TRUNCATED: usize = 7;
+
+// IUPAC nucleotide codes and gaps, in either case
+const SEQ_CHARS: &[u8] = b"ACGTUNRYSWKMBDHV-";
+
+// lines of a reader, with their line numbers and byte offsets in the decompressed stream
+struct Lines {
+    reader: BufReader<Box<dyn Read + Send>>,
+    buf: Vec<u8>, // the current line without the line ending
+    no: u64,
+    offset: u64,
+    next_offset: u64,
+    crlf: Option<(u64, u64, u64)>, // (record, line, offset) of the first CRLF line
+    crlf_count: u64,
+    record: u64, // index of the record in progress, set by the caller
+    err: Option<io::Error>,
+}
+
+impl Lines {
+    fn new(reader: Box<dyn Read + Send>) -> Self {
+        Self {
+            reader: BufReader::with_capacity(1 << 20, reader),
+            buf: Vec::new(),
+            no: 0,
+            offset: 0,
+            next_offset: 0,
+            crlf: None,
+            crlf_count: 0,
+            record: 0,
+            err: None,
+        }
+    }
+
+    // read the next line into buf, false at EOF or on a read error
+    fn next(&mut self) -> bool {
+        self.buf.clear();
+        match self.reader.read_until(b'\n', &mut self.buf) {
+            Ok(0) => false,
+            Ok(n) => {
+                self.no += 1;
+                self.offset = self.next_offset;
+                self.next_offset += n as u64;
+                if self.buf.last() == Some(&b'\n') {
+                    self.buf.pop();
+                }
+                if self.buf.last() == Some(&b'\r') {
+                    self.buf.pop();
+                    self.crlf_count += 1;
+                    self.crlf.get_or_insert((self.record, self.no, self.offset));
+                }
+                true
+            }
+            Err(e) => {
+                self.err = Some(e);
+                false
+            }
+        }
+    }
+
+    fn starts_with(&self, chars: &[u8]) -> bool {
+        self.buf.first().is_some_and(|c| chars.contains(c))
+    }
+}
+
+struct Validator {
+    counts: [u64; KINDS.len()],
+    file: String, // the file in progress
+    files: usize,
+    ids: HashMap<String, u64>, // the first record of each ID in the current file
+    records: u64,
+}
+
+impl Validator {
+    fn new() -> Self {
+        Self {
+            counts: [0; KINDS.len()],
+            file: String::new(),
+            files: 0,
+            ids: HashMap::new(),
+            records: 0,
+        }
+    }
+
+    fn report(&mut self, kind: usize, record: u64, line: u64, offset: u64, msg: &str) {
+        self.counts[kind] += 1;
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.file, record, line, offset, KINDS[kind], msg
+        );
+    }
+
+    fn check_chars(&mut self, lines: &Lines, reported: &mut bool, is_qual: bool) {
+        if *reported {
+            return;
+        }
+        let bad = if is_qual {
+            lines.buf.iter().position(|c| !(b'!'..=b'~').contains(c))
+        } else {
+            lines
+                .buf
+                .iter()
+                .position(|c| !SEQ_CHARS.contains(&c.to_ascii_uppercase()))
+        };
+        if let Some(i) = bad {
+            *reported = true;
+            let msg = format!(
+                "illegal {} char {:?} at column {}, reported once per record",
+                if is_qual { "quality" } else { "sequence" },
+                lines.buf[i] as char,
+                i + 1
+            );
+            self.report(
+                ILLEGAL_CHAR,
+                lines.record,
+                lines.no,
+                lines.offset + i as u64,
+                &msg,
+            );
+        }
+    }
+
+    fn validate_reader(&mut self, file: String, reader: Box<dyn Read + Send>) {
+        self.file = file;
+        self.files += 1;
+        self.ids.clear();
+        let mut lines = Lines::new(reader);
+        let mut record = 0;
+        let mut have = lines.next();
+        while have {
+            if lines.buf.is_empty() {
+                // blank lines between records are allowed
+                have = lines.next();
+                continue;
+            }
+            if !lines.starts_with(b">@") {
+                self.report(
+                    BAD_FORMAT,
+                    record + 1,
+                    lines.no,
+                    lines.offset,
+                    "expected '>' or '@' at the start of a record",
+                );
+                have = lines.next();
+                while have && !lines.starts_with(b">@") {
+                    have = lines.next();
+                }
+                continue;
+            }
+
+            record += 1;
+            lines.record = record;
+            if let Some(crlf) = lines.crlf.as_mut().filter(|x| x.1 == lines.no) {
+                crlf.0 = record; // the CRLF header line was read before its record began
+            }
+            self.records += 1;
+            let is_fastq = lines.buf[0] == b'@';
+            let (head_no, head_offset) = (lines.no, lines.offset);
+            let id = lines.buf[1..]
+                .split(|c| c.is_ascii_whitespace())
+                .next()
+                .unwrap_or_default();
+            let id = String::from_utf8_lossy(id).into_owned();
+            if id.is_empty() {
+                self.report(BAD_FORMAT, record, head_no, head_offset, "empty ID");
+            } else if let Some(first) = self.ids.get(&id) {
+                let msg = format!("duplicate ID {id}, first seen at record {first}");
+                self.report(DUPLICATE_ID, record, head_no, head_offset, &msg);
+            } else {
+                self.ids.insert(id, record);
+            }
+
+            // sequence lines
+            let mut seq_len = 0;
+            let mut reported = false;
+            let ends = if is_fastq { &b">@+"[..] } else { &b">@"[..] };
+            have = lines.next();
+            while have && !lines.starts_with(ends) {
+                self.check_chars(&lines, &mut reported, false);
+                seq_len += lines.buf.len();
+                have = lines.next();
+            }
+            if lines.err.is_some() {
+                break;
+            }
+            if is_fastq && !have {
+                let msg = "the file ends before the '+' line";
+                self.report(TRUNCATED, record, lines.no + 1, lines.next_offset, msg);
+                break;
+            }
+            if seq_len == 0 {
+                self.report(EMPTY_RECORD, record, head_no, head_offset, "empty sequence");
+            }
+            if !is_fastq {
+                continue;
+            }
+
+            // separator and quality lines, quality lines may start with '@' or '+'
+            if !lines.starts_with(b"+") {
+                let msg = "missing '+' line";
+                self.report(MISSING_PLUS, record, lines.no, lines.offset, msg);
+                continue;
+            }
+            let mut qual_len = 0;
+            reported = false;
+            have = lines.next();
+            let (qual_no, qual_offset) = (lines.no, lines.offset);
+            // a quality line starting with '@' after the first one is taken as the next record
+            while have && qual_len < seq_len && !(qual_len > 0 && lines.starts_with(b"@")) {
+                self.check_chars(&lines, &mut reported, true);
+                qual_len += lines.buf.len();
+                have = lines.next();
+            }
+            if lines.err.is_some() {
+                break;
+            }
+            if qual_len < seq_len && !have {
+                let msg = format!("the file ends after {qual_len} of {seq_len} quality values");
+                self.report(TRUNCATED, record, lines.no + 1, lines.next_offset, &msg);
+            } else if qual_len != seq_len {
+                let msg = format!("sequence length {seq_len} != quality length {qual_len}");
+                self.report(LENGTH_MISMATCH, record, qual_no, qual_offset, &msg);
+            }
+        }
+
+        if lines.next_offset == 0 && lines.err.is_none() {
+            self.report(EMPTY_RECORD, 0, 1, 0, "empty file");
+        }
+        if let Some((record, no, offset)) = lines.crlf {
+            let msg = format!(
+                "Windows line endings (CRLF) on {} lines, reported at the first one",
+                lines.crlf_count
+            );
+            self.report(CRLF, record, no, offset, &msg);
+        }
+        if let Some(e) = lines.err.take() {
+            let msg = format!("failed to read the rest of the file, truncated or corrupt: {e}");
+            self.report(TRUNCATED, record, lines.no + 1, lines.next_offset, &msg);
+        }
+    }
+}
+
+// check FASTA/Q files strictly, print one line per problem:
+// file, record, line, byte offset (0-based, in the decompressed stream), kind and message,
+// exit with 1 if any problem is found, each path is checked as a FASTA/Q file, never as a fofn
+pub fn validate(paths: &[&str]) {
+    let mut validator = Validator::new();
+    for path in paths {
+        validator.validate_reader(path.to_string(), open_file(path, 1));
+    }
+
+    let total: u64 = validator.counts.iter().sum();
+    eprintln!(
+        "checked {} records in {} files, found {} problems",
+        validator.records, validator.files, total
+    );
+    for (kind, count) in KINDS.iter().zip(validator.counts) {
+        if count > 0 {
+            eprintln!("{kind}: {count}");
+        }
+    }
+    if total > 0 {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, path::PathBuf};
+
+    fn check(name: &str, content: &[u8]) -> [u64; KINDS.len()] {
+        let path: PathBuf = std::env::temp_dir().join(format!(
+            "fxTools.validate.{}.{name}",
+            std::process::id()
+        ));
+        fs::write(&path, content).unwrap();
+        let path = path.to_str().unwrap();
+        let mut validator = Validator::new();
+        validator.validate_reader(path.to_string(), open_file(path, 1));
+        fs::remove_file(path).unwrap();
+        validator.counts
+    }
+
+    #[test]
+    fn empty_file() {
+        let counts = check("empty", b"");
+        assert_eq!(counts[EMPTY_RECORD], 1);
+        assert_eq!(counts.iter().sum::<u64>(), 1);
+    }
+
+    #[test]
+    fn one_byte_file() {
+        let counts = check("one_byte", b"x");
+        assert_eq!(counts[BAD_FORMAT], 1);
+    }
+
+    #[test]
+    fn not_read_as_fofn() {
+        let counts = check("fofn_like", b"hello\n>x\nACGT\n");
+        assert_eq!(counts[BAD_FORMAT], 1);
+        assert_eq!(counts.iter().sum::<u64>(), 1);
+    }
+}